@@ -1,9 +1,22 @@
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "cli")]
 use std::fs;
+use std::path::Path;
 #[cfg(feature = "cli")]
 use std::path::PathBuf;
 use anyhow::Result;
+use crate::core::types::{UploadSchedule, VerifyMode};
+use crate::core::utils::{parse_base_url, parse_proxy_url, parse_size, validate_filename_template};
+
+pub const DEFAULT_BASE_URL: &str = "https://dash.bunkr.cr";
+pub const DEFAULT_DOWNLOAD_API_BASE: &str = "https://dl.bunkr.cr";
+pub const DEFAULT_REQUEST_TIMEOUT_SECS: u64 = 300;
+pub const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 30;
+pub const DEFAULT_RESULTS_FILE: &str = "successful_uploads.txt";
+pub const DEFAULT_FAILURES_FILE: &str = "failed_uploads.txt";
+pub const DEFAULT_MAX_SIZE_MARGIN: f64 = 0.95;
+pub const DEFAULT_UI_REFRESH_MS: u64 = 100;
+pub const DEFAULT_USER_AGENT: &str = concat!("bunkr-client/", env!("CARGO_PKG_VERSION"));
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Config {
@@ -11,6 +24,68 @@ pub struct Config {
     pub default_album_id: Option<String>,
     pub default_album_name: Option<String>,
     pub preprocess_videos: Option<bool>,
+    pub max_requests_per_second: Option<u32>,
+    pub base_url: Option<String>,
+    pub download_api_base: Option<String>,
+    /// Timeout for a single HTTP request, so chunked uploads are bounded per chunk rather than per file.
+    pub request_timeout_secs: Option<u64>,
+    pub connect_timeout_secs: Option<u64>,
+    /// Upload chunk size such as "50MB", clamped to the server's maximum. Uses the server default when unset.
+    pub chunk_size: Option<String>,
+    /// Sends each file's SHA-256 with the upload. Files that fit in one chunk are read into memory to
+    /// hash them before sending, so this uses up to a chunk of memory per concurrent upload.
+    pub send_file_hash: Option<bool>,
+    /// File that successful uploads are appended to as `path\turl` lines.
+    pub results_file: Option<String>,
+    /// Age for temporary uploads, must be one of the server's `temporaryUploadAges`. Uploads are permanent when unset.
+    pub default_upload_age: Option<i64>,
+    /// Ask the server to strip (true) or keep (false) metadata such as EXIF. Ignored when the server forces its own setting.
+    pub strip_tags: Option<bool>,
+    /// Plain file containing the API token, for headless machines without a keyring.
+    pub token_file: Option<String>,
+    /// Keep going when the server reports maintenance mode instead of refusing to start.
+    pub ignore_maintenance: Option<bool>,
+    /// Glob patterns that files found in directories must match, e.g. `*.mp4`.
+    pub default_include: Option<Vec<String>>,
+    /// Glob patterns that skip files found in directories.
+    pub default_exclude: Option<Vec<String>>,
+    /// Stop the batch at the first failed file and cancel uploads still in progress.
+    pub fail_fast: Option<bool>,
+    /// File that failed uploads are written to. A `.jsonl` copy is written next to it.
+    pub failures_file: Option<String>,
+    /// Order files of a batch are started in, e.g. smallest-first.
+    pub upload_schedule: Option<UploadSchedule>,
+    /// Append ` (2)`, ` (3)`, ... to the uploaded name when a file's name was already used in the batch.
+    pub rename_duplicates: Option<bool>,
+    /// Check each upload against the stored file, costs an extra request per file.
+    pub verify_uploads: Option<VerifyMode>,
+    /// Proxy for plain HTTP requests, e.g. `http://proxy:8080` or `socks5h://127.0.0.1:9050`.
+    /// Setting either proxy key replaces the `HTTP_PROXY`/`HTTPS_PROXY` environment variables.
+    pub http_proxy: Option<String>,
+    /// Proxy for HTTPS requests, which is every Bunkr API call, upload and download.
+    pub https_proxy: Option<String>,
+    /// Skip TLS certificate checks, only for testing against self-hosted instances with self-signed certificates.
+    pub danger_accept_invalid_certs: Option<bool>,
+    /// User-Agent sent with every request, for servers that only accept specific clients.
+    pub user_agent: Option<String>,
+    /// Cap on the combined upload speed of all files, in bytes per second.
+    pub max_upload_bytes_per_sec: Option<u64>,
+    /// Split or reject files above this size instead of the server's limit. Never raises the limit above the server's.
+    pub max_file_size: Option<String>,
+    /// Fraction of the server's size limit to use, leaving the rest as headroom for request overhead.
+    pub max_size_margin: Option<f64>,
+    /// Command run on every file before upload, e.g. `zip -j {outdir}/out.zip {input}`. The files it writes to `{outdir}` are uploaded instead.
+    pub preprocess_command: Option<String>,
+    /// Where downloads are saved inside the output directory, e.g. `{album}/{index}_{name}`. Defaults to `{name}`.
+    pub download_template: Option<String>,
+    /// Randomize retry delays so parallel uploads don't retry in lockstep. Turn off for predictable timing.
+    pub retry_jitter: Option<bool>,
+    /// Upload cache used with `--use-cache`. Defaults to `bunkr_client_cache.json` in the cache directory.
+    pub cache_file: Option<String>,
+    /// How often the TUI checks for input and redraws after a change, in milliseconds.
+    pub ui_refresh_ms: Option<u64>,
+    /// After a run without failures, save its album and batch size as `default_album_id` and `default_batch_size`.
+    pub remember_last: Option<bool>,
 }
 
 impl Default for Config {
@@ -20,6 +95,38 @@ impl Default for Config {
             default_album_id: None,
             default_album_name: None,
             preprocess_videos: Some(true),
+            max_requests_per_second: None,
+            base_url: None,
+            download_api_base: None,
+            request_timeout_secs: None,
+            connect_timeout_secs: None,
+            chunk_size: None,
+            send_file_hash: Some(false),
+            results_file: None,
+            default_upload_age: None,
+            strip_tags: None,
+            token_file: None,
+            ignore_maintenance: Some(false),
+            default_include: None,
+            default_exclude: None,
+            fail_fast: Some(false),
+            failures_file: None,
+            upload_schedule: None,
+            rename_duplicates: Some(false),
+            verify_uploads: None,
+            http_proxy: None,
+            https_proxy: None,
+            danger_accept_invalid_certs: Some(false),
+            user_agent: None,
+            max_upload_bytes_per_sec: None,
+            max_file_size: None,
+            max_size_margin: Some(DEFAULT_MAX_SIZE_MARGIN),
+            preprocess_command: None,
+            download_template: None,
+            retry_jitter: Some(true),
+            cache_file: None,
+            ui_refresh_ms: None,
+            remember_last: None,
         }
     }
 }
@@ -30,15 +137,79 @@ enum ConfigKey {
     DefaultAlbumId,
     DefaultAlbumName,
     PreprocessVideos,
+    MaxRequestsPerSecond,
+    BaseUrl,
+    DownloadApiBase,
+    RequestTimeoutSecs,
+    ConnectTimeoutSecs,
+    ChunkSize,
+    SendFileHash,
+    ResultsFile,
+    DefaultUploadAge,
+    StripTags,
+    TokenFile,
+    IgnoreMaintenance,
+    DefaultInclude,
+    DefaultExclude,
+    FailFast,
+    FailuresFile,
+    UploadSchedule,
+    RenameDuplicates,
+    VerifyUploads,
+    HttpProxy,
+    HttpsProxy,
+    DangerAcceptInvalidCerts,
+    UserAgent,
+    MaxUploadBytesPerSec,
+    MaxFileSize,
+    MaxSizeMargin,
+    PreprocessCommand,
+    DownloadTemplate,
+    RetryJitter,
+    CacheFile,
+    UiRefreshMs,
+    RememberLast,
 }
 
 impl ConfigKey {
-    fn as_str(&self) -> &str {
+    fn as_str(&self) -> &'static str {
         match self {
             ConfigKey::DefaultBatchSize => "default_batch_size",
             ConfigKey::DefaultAlbumId => "default_album_id",
             ConfigKey::DefaultAlbumName => "default_album_name",
             ConfigKey::PreprocessVideos => "preprocess_videos",
+            ConfigKey::MaxRequestsPerSecond => "max_requests_per_second",
+            ConfigKey::BaseUrl => "base_url",
+            ConfigKey::DownloadApiBase => "download_api_base",
+            ConfigKey::RequestTimeoutSecs => "request_timeout_secs",
+            ConfigKey::ConnectTimeoutSecs => "connect_timeout_secs",
+            ConfigKey::ChunkSize => "chunk_size",
+            ConfigKey::SendFileHash => "send_file_hash",
+            ConfigKey::ResultsFile => "results_file",
+            ConfigKey::DefaultUploadAge => "default_upload_age",
+            ConfigKey::StripTags => "strip_tags",
+            ConfigKey::TokenFile => "token_file",
+            ConfigKey::IgnoreMaintenance => "ignore_maintenance",
+            ConfigKey::DefaultInclude => "default_include",
+            ConfigKey::DefaultExclude => "default_exclude",
+            ConfigKey::FailFast => "fail_fast",
+            ConfigKey::FailuresFile => "failures_file",
+            ConfigKey::UploadSchedule => "upload_schedule",
+            ConfigKey::RenameDuplicates => "rename_duplicates",
+            ConfigKey::VerifyUploads => "verify_uploads",
+            ConfigKey::HttpProxy => "http_proxy",
+            ConfigKey::HttpsProxy => "https_proxy",
+            ConfigKey::DangerAcceptInvalidCerts => "danger_accept_invalid_certs",
+            ConfigKey::UserAgent => "user_agent",
+            ConfigKey::MaxUploadBytesPerSec => "max_upload_bytes_per_sec",
+            ConfigKey::MaxFileSize => "max_file_size",
+            ConfigKey::MaxSizeMargin => "max_size_margin",
+            ConfigKey::PreprocessCommand => "preprocess_command",
+            ConfigKey::DownloadTemplate => "download_template",
+            ConfigKey::RetryJitter => "retry_jitter",
+            ConfigKey::CacheFile => "cache_file",
+            ConfigKey::UiRefreshMs => "ui_refresh_ms",
+            ConfigKey::RememberLast => "remember_last",
         }
     }
 
@@ -48,6 +219,38 @@ impl ConfigKey {
             "default_album_id" => Some(ConfigKey::DefaultAlbumId),
             "default_album_name" => Some(ConfigKey::DefaultAlbumName),
             "preprocess_videos" => Some(ConfigKey::PreprocessVideos),
+            "max_requests_per_second" => Some(ConfigKey::MaxRequestsPerSecond),
+            "base_url" => Some(ConfigKey::BaseUrl),
+            "download_api_base" => Some(ConfigKey::DownloadApiBase),
+            "request_timeout_secs" => Some(ConfigKey::RequestTimeoutSecs),
+            "connect_timeout_secs" => Some(ConfigKey::ConnectTimeoutSecs),
+            "chunk_size" => Some(ConfigKey::ChunkSize),
+            "send_file_hash" => Some(ConfigKey::SendFileHash),
+            "results_file" => Some(ConfigKey::ResultsFile),
+            "default_upload_age" => Some(ConfigKey::DefaultUploadAge),
+            "strip_tags" => Some(ConfigKey::StripTags),
+            "token_file" => Some(ConfigKey::TokenFile),
+            "ignore_maintenance" => Some(ConfigKey::IgnoreMaintenance),
+            "default_include" => Some(ConfigKey::DefaultInclude),
+            "default_exclude" => Some(ConfigKey::DefaultExclude),
+            "fail_fast" => Some(ConfigKey::FailFast),
+            "failures_file" => Some(ConfigKey::FailuresFile),
+            "upload_schedule" => Some(ConfigKey::UploadSchedule),
+            "rename_duplicates" => Some(ConfigKey::RenameDuplicates),
+            "verify_uploads" => Some(ConfigKey::VerifyUploads),
+            "http_proxy" => Some(ConfigKey::HttpProxy),
+            "https_proxy" => Some(ConfigKey::HttpsProxy),
+            "danger_accept_invalid_certs" => Some(ConfigKey::DangerAcceptInvalidCerts),
+            "user_agent" => Some(ConfigKey::UserAgent),
+            "max_upload_bytes_per_sec" => Some(ConfigKey::MaxUploadBytesPerSec),
+            "max_file_size" => Some(ConfigKey::MaxFileSize),
+            "max_size_margin" => Some(ConfigKey::MaxSizeMargin),
+            "preprocess_command" => Some(ConfigKey::PreprocessCommand),
+            "download_template" => Some(ConfigKey::DownloadTemplate),
+            "retry_jitter" => Some(ConfigKey::RetryJitter),
+            "cache_file" => Some(ConfigKey::CacheFile),
+            "ui_refresh_ms" => Some(ConfigKey::UiRefreshMs),
+            "remember_last" => Some(ConfigKey::RememberLast),
             _ => None,
         }
     }
@@ -58,22 +261,239 @@ impl ConfigKey {
             ConfigKey::DefaultAlbumId => config.default_album_id.clone().unwrap_or_else(|| "none".to_string()),
             ConfigKey::DefaultAlbumName => config.default_album_name.clone().unwrap_or_else(|| "none".to_string()),
             ConfigKey::PreprocessVideos => config.preprocess_videos.map(|v| v.to_string()).unwrap_or_else(|| "true".to_string()),
+            ConfigKey::MaxRequestsPerSecond => config.max_requests_per_second.map(|v| v.to_string()).unwrap_or_else(|| "none".to_string()),
+            ConfigKey::BaseUrl => config.base_url.clone().unwrap_or_else(|| DEFAULT_BASE_URL.to_string()),
+            ConfigKey::DownloadApiBase => config.download_api_base.clone().unwrap_or_else(|| DEFAULT_DOWNLOAD_API_BASE.to_string()),
+            ConfigKey::RequestTimeoutSecs => config.request_timeout_secs.unwrap_or(DEFAULT_REQUEST_TIMEOUT_SECS).to_string(),
+            ConfigKey::ConnectTimeoutSecs => config.connect_timeout_secs.unwrap_or(DEFAULT_CONNECT_TIMEOUT_SECS).to_string(),
+            ConfigKey::ChunkSize => config.chunk_size.clone().unwrap_or_else(|| "server".to_string()),
+            ConfigKey::SendFileHash => config.send_file_hash.map(|v| v.to_string()).unwrap_or_else(|| "false".to_string()),
+            ConfigKey::ResultsFile => config.results_file.clone().unwrap_or_else(|| DEFAULT_RESULTS_FILE.to_string()),
+            ConfigKey::DefaultUploadAge => config.default_upload_age.map(|v| v.to_string()).unwrap_or_else(|| "none".to_string()),
+            ConfigKey::StripTags => config.strip_tags.map(|v| v.to_string()).unwrap_or_else(|| "server".to_string()),
+            ConfigKey::TokenFile => config.token_file.clone().unwrap_or_else(|| "none".to_string()),
+            ConfigKey::IgnoreMaintenance => config.ignore_maintenance.map(|v| v.to_string()).unwrap_or_else(|| "false".to_string()),
+            ConfigKey::DefaultInclude => config.default_include.as_ref().map(|v| v.join(",")).unwrap_or_else(|| "none".to_string()),
+            ConfigKey::DefaultExclude => config.default_exclude.as_ref().map(|v| v.join(",")).unwrap_or_else(|| "none".to_string()),
+            ConfigKey::FailFast => config.fail_fast.map(|v| v.to_string()).unwrap_or_else(|| "false".to_string()),
+            ConfigKey::FailuresFile => config.failures_file.clone().unwrap_or_else(|| DEFAULT_FAILURES_FILE.to_string()),
+            ConfigKey::UploadSchedule => config.upload_schedule.unwrap_or_default().to_string(),
+            ConfigKey::RenameDuplicates => config.rename_duplicates.map(|v| v.to_string()).unwrap_or_else(|| "false".to_string()),
+            ConfigKey::VerifyUploads => config.verify_uploads.map(|v| v.to_string()).unwrap_or_else(|| "none".to_string()),
+            ConfigKey::HttpProxy => config.http_proxy.clone().unwrap_or_else(|| "none".to_string()),
+            ConfigKey::HttpsProxy => config.https_proxy.clone().unwrap_or_else(|| "none".to_string()),
+            ConfigKey::DangerAcceptInvalidCerts => config.danger_accept_invalid_certs.map(|v| v.to_string()).unwrap_or_else(|| "false".to_string()),
+            ConfigKey::UserAgent => config.user_agent.clone().unwrap_or_else(|| DEFAULT_USER_AGENT.to_string()),
+            ConfigKey::MaxUploadBytesPerSec => config.max_upload_bytes_per_sec.map(|v| v.to_string()).unwrap_or_else(|| "none".to_string()),
+            ConfigKey::MaxFileSize => config.max_file_size.clone().unwrap_or_else(|| "server".to_string()),
+            ConfigKey::MaxSizeMargin => config.max_size_margin.unwrap_or(DEFAULT_MAX_SIZE_MARGIN).to_string(),
+            ConfigKey::PreprocessCommand => config.preprocess_command.clone().unwrap_or_else(|| "none".to_string()),
+            ConfigKey::DownloadTemplate => config.download_template.clone().unwrap_or_else(|| "{name}".to_string()),
+            ConfigKey::RetryJitter => config.retry_jitter.map(|v| v.to_string()).unwrap_or_else(|| "true".to_string()),
+            ConfigKey::CacheFile => config.cache_file.clone().unwrap_or_else(|| "default".to_string()),
+            ConfigKey::UiRefreshMs => config.ui_refresh_ms.unwrap_or(DEFAULT_UI_REFRESH_MS).to_string(),
+            ConfigKey::RememberLast => config.remember_last.map(|v| v.to_string()).unwrap_or_else(|| "false".to_string()),
+        }
+    }
+
+    /// `get`, marked as the default when the key isn't set in `stored`.
+    fn display(&self, config: &Config, stored: &serde_json::Map<String, serde_json::Value>) -> String {
+        match stored.get(self.as_str()) {
+            Some(value) if !value.is_null() => self.get(config),
+            _ => format!("{} (default)", self.get(config)),
+        }
+    }
+
+    fn reset(&self, config: &mut Config) {
+        let defaults = Config::default();
+        match self {
+            ConfigKey::DefaultBatchSize => config.default_batch_size = defaults.default_batch_size,
+            ConfigKey::DefaultAlbumId => config.default_album_id = defaults.default_album_id,
+            ConfigKey::DefaultAlbumName => config.default_album_name = defaults.default_album_name,
+            ConfigKey::PreprocessVideos => config.preprocess_videos = defaults.preprocess_videos,
+            ConfigKey::MaxRequestsPerSecond => config.max_requests_per_second = defaults.max_requests_per_second,
+            ConfigKey::BaseUrl => config.base_url = defaults.base_url,
+            ConfigKey::DownloadApiBase => config.download_api_base = defaults.download_api_base,
+            ConfigKey::RequestTimeoutSecs => config.request_timeout_secs = defaults.request_timeout_secs,
+            ConfigKey::ConnectTimeoutSecs => config.connect_timeout_secs = defaults.connect_timeout_secs,
+            ConfigKey::ChunkSize => config.chunk_size = defaults.chunk_size,
+            ConfigKey::SendFileHash => config.send_file_hash = defaults.send_file_hash,
+            ConfigKey::ResultsFile => config.results_file = defaults.results_file,
+            ConfigKey::DefaultUploadAge => config.default_upload_age = defaults.default_upload_age,
+            ConfigKey::StripTags => config.strip_tags = defaults.strip_tags,
+            ConfigKey::TokenFile => config.token_file = defaults.token_file,
+            ConfigKey::IgnoreMaintenance => config.ignore_maintenance = defaults.ignore_maintenance,
+            ConfigKey::DefaultInclude => config.default_include = defaults.default_include,
+            ConfigKey::DefaultExclude => config.default_exclude = defaults.default_exclude,
+            ConfigKey::FailFast => config.fail_fast = defaults.fail_fast,
+            ConfigKey::FailuresFile => config.failures_file = defaults.failures_file,
+            ConfigKey::UploadSchedule => config.upload_schedule = defaults.upload_schedule,
+            ConfigKey::RenameDuplicates => config.rename_duplicates = defaults.rename_duplicates,
+            ConfigKey::VerifyUploads => config.verify_uploads = defaults.verify_uploads,
+            ConfigKey::HttpProxy => config.http_proxy = defaults.http_proxy,
+            ConfigKey::HttpsProxy => config.https_proxy = defaults.https_proxy,
+            ConfigKey::DangerAcceptInvalidCerts => config.danger_accept_invalid_certs = defaults.danger_accept_invalid_certs,
+            ConfigKey::UserAgent => config.user_agent = defaults.user_agent,
+            ConfigKey::MaxUploadBytesPerSec => config.max_upload_bytes_per_sec = defaults.max_upload_bytes_per_sec,
+            ConfigKey::MaxFileSize => config.max_file_size = defaults.max_file_size,
+            ConfigKey::MaxSizeMargin => config.max_size_margin = defaults.max_size_margin,
+            ConfigKey::PreprocessCommand => config.preprocess_command = defaults.preprocess_command,
+            ConfigKey::DownloadTemplate => config.download_template = defaults.download_template,
+            ConfigKey::RetryJitter => config.retry_jitter = defaults.retry_jitter,
+            ConfigKey::CacheFile => config.cache_file = defaults.cache_file,
+            ConfigKey::UiRefreshMs => config.ui_refresh_ms = defaults.ui_refresh_ms,
+            ConfigKey::RememberLast => config.remember_last = defaults.remember_last,
         }
     }
 
     fn set(&self, config: &mut Config, value: &str) -> Result<()> {
+        if value == "default" {
+            self.reset(config);
+            return Ok(());
+        }
+        let key = self.as_str();
         match self {
             ConfigKey::DefaultBatchSize => {
-                config.default_batch_size = Some(value.parse()?);
+                config.default_batch_size = Some(parse_number(key, value, 1)?);
             }
             ConfigKey::DefaultAlbumId => {
-                config.default_album_id = if value == "none" { None } else { Some(value.to_string()) };
+                config.default_album_id = if value == "none" {
+                    None
+                } else {
+                    parse_number::<i64>(key, value, 0)?;
+                    Some(value.to_string())
+                };
             }
             ConfigKey::DefaultAlbumName => {
                 config.default_album_name = if value == "none" { None } else { Some(value.to_string()) };
             }
             ConfigKey::PreprocessVideos => {
-                config.preprocess_videos = Some(value.parse()?);
+                config.preprocess_videos = Some(parse_bool(key, value)?);
+            }
+            ConfigKey::MaxRequestsPerSecond => {
+                config.max_requests_per_second = if value == "none" { None } else { Some(parse_number(key, value, 1)?) };
+            }
+            ConfigKey::BaseUrl => {
+                config.base_url = if value == "none" { None } else { Some(parse_url(key, value)?) };
+            }
+            ConfigKey::DownloadApiBase => {
+                config.download_api_base = if value == "none" { None } else { Some(parse_url(key, value)?) };
+            }
+            ConfigKey::RequestTimeoutSecs => {
+                config.request_timeout_secs = Some(parse_number(key, value, 1)?);
+            }
+            ConfigKey::ConnectTimeoutSecs => {
+                config.connect_timeout_secs = Some(parse_number(key, value, 1)?);
+            }
+            ConfigKey::ChunkSize => {
+                config.chunk_size = if value == "none" || value == "server" {
+                    None
+                } else {
+                    match parse_size(value) {
+                        Ok(size) if size > 0 => Some(value.to_string()),
+                        _ => return Err(invalid_value(key, value, "a size such as 25MB or 1GB, or server")),
+                    }
+                };
+            }
+            ConfigKey::SendFileHash => {
+                config.send_file_hash = Some(parse_bool(key, value)?);
+            }
+            ConfigKey::ResultsFile => {
+                config.results_file = if value == "none" { None } else { Some(value.to_string()) };
+            }
+            ConfigKey::DefaultUploadAge => {
+                config.default_upload_age = if value == "none" { None } else { Some(parse_number(key, value, 0)?) };
+            }
+            ConfigKey::StripTags => {
+                config.strip_tags = if value == "none" || value == "server" { None } else { Some(parse_bool(key, value)?) };
+            }
+            ConfigKey::TokenFile => {
+                config.token_file = if value == "none" { None } else { Some(value.to_string()) };
+            }
+            ConfigKey::IgnoreMaintenance => {
+                config.ignore_maintenance = Some(parse_bool(key, value)?);
+            }
+            ConfigKey::DefaultInclude => {
+                config.default_include = if value == "none" { None } else { Some(parse_list(value)) };
+            }
+            ConfigKey::DefaultExclude => {
+                config.default_exclude = if value == "none" { None } else { Some(parse_list(value)) };
+            }
+            ConfigKey::FailFast => {
+                config.fail_fast = Some(parse_bool(key, value)?);
+            }
+            ConfigKey::FailuresFile => {
+                config.failures_file = if value == "none" { None } else { Some(value.to_string()) };
+            }
+            ConfigKey::UploadSchedule => {
+                let schedule = value.parse().map_err(|_| invalid_value(key, value, "one of input, largest-first, smallest-first, interleaved"))?;
+                config.upload_schedule = Some(schedule);
+            }
+            ConfigKey::RenameDuplicates => {
+                config.rename_duplicates = Some(parse_bool(key, value)?);
+            }
+            ConfigKey::VerifyUploads => {
+                config.verify_uploads = if value == "none" {
+                    None
+                } else {
+                    Some(value.parse().map_err(|_| invalid_value(key, value, "one of size, hash, none"))?)
+                };
+            }
+            ConfigKey::HttpProxy => {
+                config.http_proxy = if value == "none" { None } else { Some(parse_proxy(key, value)?) };
+            }
+            ConfigKey::HttpsProxy => {
+                config.https_proxy = if value == "none" { None } else { Some(parse_proxy(key, value)?) };
+            }
+            ConfigKey::DangerAcceptInvalidCerts => {
+                config.danger_accept_invalid_certs = Some(parse_bool(key, value)?);
+            }
+            ConfigKey::UserAgent => {
+                if value.trim().is_empty() || reqwest::header::HeaderValue::from_str(value).is_err() {
+                    return Err(invalid_value(key, value, "a non-empty header value"));
+                }
+                config.user_agent = Some(value.to_string());
+            }
+            ConfigKey::MaxUploadBytesPerSec => {
+                config.max_upload_bytes_per_sec = if value == "none" { None } else { Some(parse_rate(key, value)?) };
+            }
+            ConfigKey::MaxFileSize => {
+                config.max_file_size = if value == "none" || value == "server" {
+                    None
+                } else {
+                    match parse_size(value) {
+                        Ok(size) if size > 0 => Some(value.to_string()),
+                        _ => return Err(invalid_value(key, value, "a size such as 500MB or 2GB, or server")),
+                    }
+                };
+            }
+            ConfigKey::MaxSizeMargin => {
+                config.max_size_margin = Some(parse_margin(key, value)?);
+            }
+            ConfigKey::PreprocessCommand => {
+                config.preprocess_command = if value == "none" {
+                    None
+                } else if value.contains("{outdir}") {
+                    Some(value.to_string())
+                } else {
+                    return Err(invalid_value(key, value, "a command that writes to {outdir}, or none"));
+                };
+            }
+            ConfigKey::DownloadTemplate => {
+                validate_filename_template(value).map_err(|e| anyhow::anyhow!("Invalid value '{}' for {}: {}", value, key, e))?;
+                config.download_template = Some(value.to_string());
+            }
+            ConfigKey::RetryJitter => {
+                config.retry_jitter = Some(parse_bool(key, value)?);
+            }
+            ConfigKey::CacheFile => {
+                config.cache_file = Some(value.to_string());
+            }
+            ConfigKey::UiRefreshMs => {
+                config.ui_refresh_ms = Some(parse_number(key, value, 10)?);
+            }
+            ConfigKey::RememberLast => {
+                config.remember_last = Some(parse_bool(key, value)?);
             }
         }
         Ok(())
@@ -85,6 +505,38 @@ impl ConfigKey {
             ConfigKey::DefaultAlbumId => "none".to_string(),
             ConfigKey::DefaultAlbumName => "none".to_string(),
             ConfigKey::PreprocessVideos => "true".to_string(),
+            ConfigKey::MaxRequestsPerSecond => "none".to_string(),
+            ConfigKey::BaseUrl => DEFAULT_BASE_URL.to_string(),
+            ConfigKey::DownloadApiBase => DEFAULT_DOWNLOAD_API_BASE.to_string(),
+            ConfigKey::RequestTimeoutSecs => DEFAULT_REQUEST_TIMEOUT_SECS.to_string(),
+            ConfigKey::ConnectTimeoutSecs => DEFAULT_CONNECT_TIMEOUT_SECS.to_string(),
+            ConfigKey::ChunkSize => "server".to_string(),
+            ConfigKey::SendFileHash => "false".to_string(),
+            ConfigKey::ResultsFile => DEFAULT_RESULTS_FILE.to_string(),
+            ConfigKey::DefaultUploadAge => "none".to_string(),
+            ConfigKey::StripTags => "server".to_string(),
+            ConfigKey::TokenFile => "none".to_string(),
+            ConfigKey::IgnoreMaintenance => "false".to_string(),
+            ConfigKey::DefaultInclude => "none".to_string(),
+            ConfigKey::DefaultExclude => "none".to_string(),
+            ConfigKey::FailFast => "false".to_string(),
+            ConfigKey::FailuresFile => DEFAULT_FAILURES_FILE.to_string(),
+            ConfigKey::UploadSchedule => UploadSchedule::Input.to_string(),
+            ConfigKey::RenameDuplicates => "false".to_string(),
+            ConfigKey::VerifyUploads => "none".to_string(),
+            ConfigKey::HttpProxy => "none".to_string(),
+            ConfigKey::HttpsProxy => "none".to_string(),
+            ConfigKey::DangerAcceptInvalidCerts => "false".to_string(),
+            ConfigKey::UserAgent => DEFAULT_USER_AGENT.to_string(),
+            ConfigKey::MaxUploadBytesPerSec => "none".to_string(),
+            ConfigKey::MaxFileSize => "server".to_string(),
+            ConfigKey::MaxSizeMargin => DEFAULT_MAX_SIZE_MARGIN.to_string(),
+            ConfigKey::PreprocessCommand => "none".to_string(),
+            ConfigKey::DownloadTemplate => "{name}".to_string(),
+            ConfigKey::RetryJitter => "true".to_string(),
+            ConfigKey::CacheFile => "default".to_string(),
+            ConfigKey::UiRefreshMs => DEFAULT_UI_REFRESH_MS.to_string(),
+            ConfigKey::RememberLast => "false".to_string(),
         }
     }
 
@@ -94,14 +546,120 @@ impl ConfigKey {
             ConfigKey::DefaultAlbumId,
             ConfigKey::DefaultAlbumName,
             ConfigKey::PreprocessVideos,
+            ConfigKey::MaxRequestsPerSecond,
+            ConfigKey::BaseUrl,
+            ConfigKey::DownloadApiBase,
+            ConfigKey::RequestTimeoutSecs,
+            ConfigKey::ConnectTimeoutSecs,
+            ConfigKey::ChunkSize,
+            ConfigKey::SendFileHash,
+            ConfigKey::ResultsFile,
+            ConfigKey::DefaultUploadAge,
+            ConfigKey::StripTags,
+            ConfigKey::TokenFile,
+            ConfigKey::IgnoreMaintenance,
+            ConfigKey::DefaultInclude,
+            ConfigKey::DefaultExclude,
+            ConfigKey::FailFast,
+            ConfigKey::FailuresFile,
+            ConfigKey::UploadSchedule,
+            ConfigKey::RenameDuplicates,
+            ConfigKey::VerifyUploads,
+            ConfigKey::HttpProxy,
+            ConfigKey::HttpsProxy,
+            ConfigKey::DangerAcceptInvalidCerts,
+            ConfigKey::UserAgent,
+            ConfigKey::MaxUploadBytesPerSec,
+            ConfigKey::MaxFileSize,
+            ConfigKey::MaxSizeMargin,
+            ConfigKey::PreprocessCommand,
+            ConfigKey::DownloadTemplate,
+            ConfigKey::RetryJitter,
+            ConfigKey::CacheFile,
+            ConfigKey::UiRefreshMs,
+            ConfigKey::RememberLast,
         ]
     }
 }
 
+fn invalid_value(key: &str, value: &str, expected: &str) -> anyhow::Error {
+    anyhow::anyhow!("Invalid value '{}' for {}: expected {}", value, key, expected)
+}
+
+fn parse_bool(key: &str, value: &str) -> Result<bool> {
+    match value.to_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Ok(true),
+        "false" | "no" | "off" | "0" => Ok(false),
+        _ => Err(invalid_value(key, value, "one of true/false, yes/no, on/off")),
+    }
+}
+
+fn parse_number<T: std::str::FromStr + PartialOrd + std::fmt::Display + Copy>(key: &str, value: &str, min: T) -> Result<T> {
+    match value.trim().parse::<T>() {
+        Ok(n) if n >= min => Ok(n),
+        _ => Err(invalid_value(key, value, &format!("a whole number of at least {}", min))),
+    }
+}
+
+/// Splits a comma-separated list, dropping empty entries. Commas inside `{}` belong to a glob
+/// alternation like `*.{mp4,mkv}` and don't split it.
+fn parse_list(value: &str) -> Vec<String> {
+    let mut items = vec![];
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in value.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                items.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&value[start..]);
+    items.into_iter().map(|v| v.trim().to_string()).filter(|v| !v.is_empty()).collect()
+}
+
+fn parse_url(key: &str, value: &str) -> Result<String> {
+    parse_base_url(value).map_err(|_| invalid_value(key, value, "an http(s) URL such as https://dash.bunkr.cr"))?;
+    Ok(value.to_string())
+}
+
+/// Accepts a plain number of bytes or a size such as 2MB, per second.
+fn parse_rate(key: &str, value: &str) -> Result<u64> {
+    match value.trim().parse::<u64>().ok().or_else(|| parse_size(value).ok()) {
+        Some(rate) if rate > 0 => Ok(rate),
+        _ => Err(invalid_value(key, value, "bytes per second such as 500000 or 2MB")),
+    }
+}
+
+/// Accepts a fraction above 0 and at most 1, such as 0.95.
+fn parse_margin(key: &str, value: &str) -> Result<f64> {
+    match value.trim().parse::<f64>() {
+        Ok(margin) if margin > 0.0 && margin <= 1.0 => Ok(margin),
+        _ => Err(invalid_value(key, value, "a fraction above 0 and at most 1, such as 0.95")),
+    }
+}
+
+fn parse_proxy(key: &str, value: &str) -> Result<String> {
+    parse_proxy_url(value).map_err(|_| invalid_value(key, value, "a proxy URL such as http://proxy:8080 or socks5h://127.0.0.1:9050"))
+}
+
 impl Config {
+    /// Loads the config from `path`, or the default location when `None`, then applies environment overrides.
     #[cfg(feature = "cli")]
-    pub fn load() -> Result<Self> {
-        let config_path = Self::config_path();
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let mut config = Self::load_file(path)?;
+        config.apply_env_overrides()?;
+        Ok(config)
+    }
+
+    /// Loads the config file without environment overrides, for editing and saving it back.
+    #[cfg(feature = "cli")]
+    pub fn load_file(path: Option<&Path>) -> Result<Self> {
+        let config_path = Self::resolve_path(path);
         if config_path.exists() {
             let content = fs::read_to_string(config_path)?;
             Ok(toml::from_str(&content)?)
@@ -111,13 +669,26 @@ impl Config {
     }
 
     #[cfg(not(feature = "cli"))]
-    pub fn load() -> Result<Self> {
-        Ok(Self::default())
+    pub fn load(_path: Option<&Path>) -> Result<Self> {
+        let mut config = Self::default();
+        config.apply_env_overrides()?;
+        Ok(config)
+    }
+
+    /// Overrides keys from `BUNKR_<KEY>` environment variables, e.g. `BUNKR_DEFAULT_BATCH_SIZE`.
+    pub fn apply_env_overrides(&mut self) -> Result<()> {
+        for key in ConfigKey::all() {
+            let var = format!("BUNKR_{}", key.as_str().to_uppercase());
+            if let Ok(value) = std::env::var(&var) {
+                key.set(self, &value).map_err(|e| anyhow::anyhow!("{} (from {})", e, var))?;
+            }
+        }
+        Ok(())
     }
 
     #[cfg(feature = "cli")]
-    pub fn save(&self) -> Result<()> {
-        let config_path = Self::config_path();
+    pub fn save(&self, path: Option<&Path>) -> Result<()> {
+        let config_path = Self::resolve_path(path);
         if let Some(parent) = config_path.parent() {
             fs::create_dir_all(parent)?;
         }
@@ -127,7 +698,7 @@ impl Config {
     }
 
     #[cfg(not(feature = "cli"))]
-    pub fn save(&self) -> Result<()> {
+    pub fn save(&self, _path: Option<&Path>) -> Result<()> {
         Err(anyhow::anyhow!("CLI feature is not enabled."))
     }
 
@@ -147,13 +718,95 @@ impl Config {
         }
     }
 
+    /// Restores a single key to its default value.
+    pub fn unset_value(&mut self, key: &str) -> Result<()> {
+        if let Some(k) = ConfigKey::from_str(key) {
+            k.reset(self);
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("Unknown key: {}", key))
+        }
+    }
+
+    /// The keys set in this config with their values, in the same form `config get` prints, for
+    /// copying settings to another machine. Keys that are unset or at their default are left out
+    /// so they keep following the defaults wherever the file is imported.
+    pub fn to_json(&self) -> serde_json::Value {
+        let stored = self.stored_values();
+        let map = ConfigKey::all().iter()
+            .filter(|key| stored.get(key.as_str()).is_some_and(|v| !v.is_null()) && key.get(self) != key.default())
+            .map(|key| (key.as_str().to_string(), serde_json::Value::String(key.get(self))))
+            .collect();
+        serde_json::Value::Object(map)
+    }
+
+    /// Applies the keys of an exported JSON object. Each value goes through the same checks as
+    /// `config set`; nothing is changed if any key is unknown or any value is invalid. A `null`
+    /// or the key's default value unsets the key instead of pinning today's default.
+    pub fn apply_json(&mut self, json: &serde_json::Value) -> Result<()> {
+        let object = json.as_object().ok_or_else(|| anyhow::anyhow!("Expected a JSON object of config keys"))?;
+        let mut updated = self.clone();
+        for (name, value) in object {
+            let key = ConfigKey::from_str(name).ok_or_else(|| anyhow::anyhow!("Unknown key: {}", name))?;
+            let value = match value {
+                serde_json::Value::Null => {
+                    key.reset(&mut updated);
+                    continue;
+                }
+                serde_json::Value::String(s) => s.clone(),
+                serde_json::Value::Array(items) => items.iter()
+                    .map(|item| item.as_str().map(str::to_string).unwrap_or_else(|| item.to_string()))
+                    .collect::<Vec<_>>()
+                    .join(","),
+                other => other.to_string(),
+            };
+            if value == key.default() {
+                key.reset(&mut updated);
+            } else {
+                key.set(&mut updated, &value)?;
+            }
+        }
+        *self = updated;
+        Ok(())
+    }
+
+    /// Returns `(key, old value, new value)` for every key whose stored value differs between the
+    /// two configs, so setting a key to its default or unsetting it counts as a change.
+    pub fn diff(&self, other: &Config) -> Vec<(&'static str, String, String)> {
+        let (old, new) = (self.stored_values(), other.stored_values());
+        ConfigKey::all().iter()
+            .filter(|key| old.get(key.as_str()) != new.get(key.as_str()))
+            .map(|key| (key.as_str(), key.display(self, &old), key.display(other, &new)))
+            .collect()
+    }
+
+    /// The fields as they're saved to the config file, `null` when unset.
+    fn stored_values(&self) -> serde_json::Map<String, serde_json::Value> {
+        match serde_json::to_value(self) {
+            Ok(serde_json::Value::Object(map)) => map,
+            _ => serde_json::Map::new(),
+        }
+    }
+
     pub fn print_all(&self) {
-        println!("Key                    Value     | Default");
-        println!("─────────────────────────────────────────");
+        println!("Key                       Value     | Default");
+        println!("────────────────────────────────────────────");
         for key in ConfigKey::all() {
             let current = key.get(self);
             let default = key.default();
-            println!("{:<22} {:<9} | \x1b[3m{}\x1b[0m", key.as_str(), current, default);
+            println!("{:<25} {:<9} | \x1b[3m{}\x1b[0m", key.as_str(), current, default);
+        }
+    }
+
+    /// An explicit path wins, then `BUNKR_CONFIG`, then the default location.
+    #[cfg(feature = "cli")]
+    fn resolve_path(path: Option<&Path>) -> PathBuf {
+        if let Some(path) = path {
+            return path.to_path_buf();
+        }
+        match std::env::var_os("BUNKR_CONFIG") {
+            Some(env_path) if !env_path.is_empty() => PathBuf::from(env_path),
+            _ => Self::config_path(),
         }
     }
 
@@ -163,4 +816,85 @@ impl Config {
             .unwrap_or_else(|| PathBuf::from("."))
             .join("bunkr_client.toml")
     }
+
+    /// Config file for a named profile, next to the default config. The default profile uses the default file.
+    #[cfg(feature = "cli")]
+    pub fn profile_path(profile: &str) -> PathBuf {
+        if profile == "default" {
+            return Self::config_path();
+        }
+        dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join(format!("bunkr_client.{}.toml", profile))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_splits_on_commas_and_drops_empty_entries() {
+        assert_eq!(parse_list("*.jpg, *.png,,"), ["*.jpg", "*.png"]);
+    }
+
+    #[test]
+    fn list_keeps_brace_alternations_whole() {
+        assert_eq!(parse_list("*.{mp4,mkv},*.txt"), ["*.{mp4,mkv}", "*.txt"]);
+        assert_eq!(parse_list("{a,{b,c}}/*, d"), ["{a,{b,c}}/*", "d"]);
+    }
+
+    #[test]
+    fn export_leaves_out_unset_and_default_keys() {
+        let config = Config {
+            user_agent: Some(DEFAULT_USER_AGENT.to_string()),
+            failures_file: Some("fails.txt".to_string()),
+            ..Config::default()
+        };
+        let json = config.to_json();
+        assert_eq!(json, serde_json::json!({ "failures_file": "fails.txt" }));
+    }
+
+    #[test]
+    fn import_unsets_keys_given_as_null_or_their_default() {
+        let mut config = Config {
+            base_url: Some("https://bunkr.example".to_string()),
+            user_agent: Some("custom/1.0".to_string()),
+            ..Config::default()
+        };
+        config.apply_json(&serde_json::json!({
+            "base_url": DEFAULT_BASE_URL,
+            "user_agent": null,
+            "ui_refresh_ms": "250",
+        })).unwrap();
+        assert_eq!(config.base_url, None);
+        assert_eq!(config.user_agent, None);
+        assert_eq!(config.ui_refresh_ms, Some(250));
+    }
+
+    #[test]
+    fn export_then_import_into_an_empty_config_reports_the_set_keys() {
+        let source = Config { chunk_size: Some("50MB".to_string()), ..Config::default() };
+        let previous = Config::default();
+        let mut imported = previous.clone();
+        imported.apply_json(&source.to_json()).unwrap();
+
+        assert_eq!(imported.base_url, None);
+        assert_eq!(imported.user_agent, None);
+        assert_eq!(previous.diff(&imported), [("chunk_size", "server (default)".to_string(), "50MB".to_string())]);
+    }
+
+    #[test]
+    fn diff_sees_a_key_set_to_its_default() {
+        let previous = Config::default();
+        let current = Config { base_url: Some(DEFAULT_BASE_URL.to_string()), ..Config::default() };
+        assert_eq!(previous.diff(&current), [("base_url", format!("{} (default)", DEFAULT_BASE_URL), DEFAULT_BASE_URL.to_string())]);
+    }
+
+    #[test]
+    fn default_include_round_trips_brace_alternations() {
+        let mut config = Config::default();
+        ConfigKey::DefaultInclude.set(&mut config, "*.{mp4,mkv}, *.jpg").unwrap();
+        assert_eq!(config.default_include.as_deref().unwrap(), ["*.{mp4,mkv}", "*.jpg"]);
+    }
 }
@@ -1,2 +1,3 @@
+#[allow(clippy::module_inception)]
 pub mod config;
 pub mod bunkr_config;
\ No newline at end of file
@@ -0,0 +1,117 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// A file uploaded in an earlier run.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub url: String,
+    /// Path, size and modification time (nanoseconds since the epoch) of the file when it was
+    /// uploaded, so an unchanged file is found again without hashing it.
+    pub path: String,
+    pub size: u64,
+    pub mtime: u64,
+}
+
+/// Upload URLs keyed by the SHA-256 of the file content, kept in a JSON file so files uploaded
+/// in an earlier run aren't uploaded again.
+pub struct UploadCache {
+    path: PathBuf,
+    entries: HashMap<String, CacheEntry>,
+}
+
+impl UploadCache {
+    /// Loads the cache stored at `path`, or starts an empty one if the file doesn't exist.
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let entries = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|e| anyhow::anyhow!("Upload cache {} is corrupt: {}", path.display(), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(anyhow::anyhow!("Failed to read upload cache {}: {}", path.display(), e)),
+        };
+        Ok(Self { path, entries })
+    }
+
+    /// Writes the cache back to where it was loaded from, replacing the file in one step.
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let temp = self.path.with_extension("json.tmp");
+        std::fs::write(&temp, serde_json::to_string(&self.entries)?)?;
+        std::fs::rename(&temp, &self.path)?;
+        Ok(())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// URL of an earlier upload with the same content as `file`. Unchanged files are matched by
+    /// path, size and modification time; other files are only hashed if an entry has their size.
+    pub fn lookup(&self, file: &Path) -> Result<Option<String>> {
+        let (size, mtime) = file_stamp(file)?;
+        let path = file.to_string_lossy();
+        if let Some(entry) = self.entries.values().find(|e| e.path == path && e.size == size && e.mtime == mtime) {
+            return Ok(Some(entry.url.clone()));
+        }
+        if !self.entries.values().any(|e| e.size == size) {
+            return Ok(None);
+        }
+        let hash = hash_file(file)?;
+        Ok(self.entries.get(&hash).filter(|e| e.size == size).map(|e| e.url.clone()))
+    }
+
+    /// Records that `file`, whose content hashes to `sha256`, was uploaded to `url`.
+    pub fn insert(&mut self, file: &Path, sha256: String, url: String) -> Result<()> {
+        let (size, mtime) = file_stamp(file)?;
+        self.entries.insert(sha256, CacheEntry { url, path: file.to_string_lossy().to_string(), size, mtime });
+        Ok(())
+    }
+
+    /// Drops entries whose file was deleted or changed since it was uploaded. Returns how many were removed.
+    pub fn prune(&mut self) -> usize {
+        let before = self.entries.len();
+        self.entries.retain(|_, e| file_stamp(Path::new(&e.path)).is_ok_and(|stamp| stamp == (e.size, e.mtime)));
+        before - self.entries.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+fn file_stamp(path: &Path) -> Result<(u64, u64)> {
+    let metadata = std::fs::metadata(path)?;
+    let mtime = metadata.modified()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0);
+    Ok((metadata.len(), mtime))
+}
+
+fn hash_file(path: &Path) -> Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
@@ -1,20 +1,33 @@
 #[cfg(feature = "download")]
 use crate::TokenResponse;
-use crate::core::types::{AlbumFile, FailedOperationInfo};
+use crate::core::types::AlbumFile;
 #[cfg(feature = "download")]
-use crate::core::types::DownloadResponse;
+use crate::core::types::{FailedOperationInfo, HttpStatusError};
+#[cfg(feature = "download")]
+use crate::core::types::{BunkrUrl, DownloadResponse};
+use crate::config::config::Config;
+#[cfg(feature = "download")]
+use crate::config::config::DEFAULT_DOWNLOAD_API_BASE;
+#[cfg(feature = "download")]
+use crate::core::utils::{parse_base_url, parse_bunkr_url, build_client, expand_filename_template};
+#[cfg(feature = "download")]
+use crate::core::http::retry_with_backoff;
 use anyhow::{Result, anyhow};
 #[cfg(feature = "download")]
 use json5;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 #[cfg(feature = "download")]
 use reqwest::{Client, header};
 #[cfg(feature = "download")]
 use regex::Regex;
 #[cfg(feature = "download")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "download")]
 use serde_json;
 #[cfg(feature = "download")]
-use std::path::Path;
+use std::collections::HashMap;
+#[cfg(feature = "download")]
+use std::path::{Path, PathBuf};
 #[cfg(feature = "download")]
 use std::sync::OnceLock;
 #[cfg(feature = "download")]
@@ -23,19 +36,85 @@ use std::time::Duration;
 use tokio::io::AsyncWriteExt;
 #[cfg(feature = "download")]
 use tokio::task::JoinSet;
+#[cfg(feature = "download")]
+use tracing::{debug, warn};
+
+use crate::core::progress::ProgressSink;
 
-#[cfg(feature = "ui")]
-use crate::ui::ui::UIState;
-#[cfg(not(feature = "ui"))]
-use crate::core::types::UIState;
-
-#[cfg(not(feature = "ui"))]
-impl UIState {
-    pub fn add_current_operation(&mut self, _name: String, _progress: f64, _size: u64) {}
-    pub fn update_progress(&mut self, _name: &str, _progress: f64) {}
-    pub fn remove_current_operation(&mut self, _name: &str, _url: Option<&str>) {}
-    pub fn add_failed_operation(&mut self, _name: String, _info: FailedOperationInfo) {}
-    pub fn add_processed_bytes(&mut self, _bytes: u64) {}
+/// Name of the file in the output directory that records which files of a download finished.
+pub const DOWNLOAD_MANIFEST: &str = ".bunkr-download.json";
+
+/// A file of a download as recorded in the manifest.
+#[cfg(feature = "download")]
+#[derive(Serialize, Deserialize)]
+struct ManifestEntry {
+    name: String,
+    /// Path relative to the output directory.
+    path: String,
+    /// Size on disk once finished, the album listing only gives an approximate one.
+    size: u64,
+    completed: bool,
+}
+
+/// Progress of the downloads into one output directory, keyed by file id, so an interrupted
+/// download resumes without fetching finished files again.
+#[cfg(feature = "download")]
+struct DownloadManifest {
+    path: PathBuf,
+    files: HashMap<String, ManifestEntry>,
+}
+
+#[cfg(feature = "download")]
+impl DownloadManifest {
+    /// Loads the manifest in `output_dir`. A missing or unreadable one starts empty.
+    fn load(output_dir: &Path) -> Self {
+        let path = output_dir.join(DOWNLOAD_MANIFEST);
+        let files = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                warn!("Ignoring corrupt download manifest {}: {}", path.display(), e);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+        Self { path, files }
+    }
+
+    /// Writes the manifest back, replacing the file in one step so an interruption can't truncate it.
+    fn save(&self) -> Result<()> {
+        let temp = self.path.with_extension("json.tmp");
+        std::fs::write(&temp, serde_json::to_string(&self.files)?)?;
+        std::fs::rename(&temp, &self.path)?;
+        Ok(())
+    }
+
+    fn save_or_warn(&self) {
+        if let Err(e) = self.save() {
+            warn!("Failed to save download manifest {}: {}", self.path.display(), e);
+        }
+    }
+
+    /// Whether `file` finished downloading to `path` and is still there as it was written.
+    fn is_complete(&self, file: &AlbumFile, path: &Path, relative: &Path) -> bool {
+        self.files.get(&file.id.to_string()).is_some_and(|entry| {
+            entry.completed
+                && Path::new(&entry.path) == relative
+                && std::fs::metadata(path).is_ok_and(|m| m.len() == entry.size)
+        })
+    }
+
+    /// Whether `file` was started in an earlier run but didn't finish.
+    fn is_partial(&self, file: &AlbumFile) -> bool {
+        self.files.get(&file.id.to_string()).is_some_and(|entry| !entry.completed)
+    }
+
+    fn record(&mut self, file: &AlbumFile, relative: &Path, size: u64, completed: bool) {
+        self.files.insert(file.id.to_string(), ManifestEntry {
+            name: file.original.clone(),
+            path: relative.to_string_lossy().to_string(),
+            size,
+            completed,
+        });
+    }
 }
 
 pub struct BunkrDownloader {
@@ -44,6 +123,8 @@ pub struct BunkrDownloader {
     #[cfg(feature = "download")]
     headers: header::HeaderMap,
     #[cfg(feature = "download")]
+    download_api_base: String,
+    #[cfg(feature = "download")]
     album_files_regex: OnceLock<Regex>,
     #[cfg(feature = "download")]
     trailing_comma_regex: OnceLock<Regex>,
@@ -53,11 +134,23 @@ pub struct BunkrDownloader {
     id_regex: OnceLock<Regex>,
     #[cfg(feature = "download")]
     orig_regex: OnceLock<Regex>,
+    #[cfg(feature = "download")]
+    size_regex: OnceLock<Regex>,
+    #[cfg(feature = "download")]
+    thumbnail_regex: OnceLock<Regex>,
+    #[cfg(feature = "download")]
+    album_title_regex: OnceLock<Regex>,
+    /// Where each file goes relative to the output directory, see `expand_filename_template`.
+    #[cfg(feature = "download")]
+    filename_template: String,
+    /// Randomize retry delays, see `http::backoff_delay`.
+    #[cfg(feature = "download")]
+    retry_jitter: bool,
 }
 
 #[cfg(not(feature = "download"))]
 impl BunkrDownloader {
-    pub async fn new() -> Result<Self> {
+    pub async fn new(_config: &Config) -> Result<Self> {
         Err(anyhow!("Download feature is not enabled."))
     }
 
@@ -65,22 +158,37 @@ impl BunkrDownloader {
         Err(anyhow!("Download feature is not enabled."))
     }
 
-    pub async fn download_file(&self, _file: &AlbumFile, _output_dir: &str, _ui_state: Option<Arc<Mutex<UIState>>>) -> Result<()> {
+    pub async fn download_file(&self, _file: &AlbumFile, _output_dir: &str, _progress: Option<Arc<dyn ProgressSink + Send + Sync>>) -> Result<()> {
         Err(anyhow!("Download feature is not enabled."))
     }
 
-    pub async fn download_files(&self, _files: Vec<AlbumFile>, _output_dir: &str, _batch_size: usize, _ui_state: Option<Arc<Mutex<UIState>>>) -> Result<()> {
+    pub fn file_path(&self, _file: &AlbumFile, _index: usize) -> std::path::PathBuf {
+        std::path::PathBuf::new()
+    }
+
+    pub async fn resolve_download_url(&self, _file: &AlbumFile) -> Result<String> {
+        Err(anyhow!("Download feature is not enabled."))
+    }
+
+    pub fn with_cookie(self, _cookie: &str) -> Result<Self> {
+        Err(anyhow!("Download feature is not enabled."))
+    }
+
+    pub async fn download_files(&self, _files: Vec<AlbumFile>, _output_dir: &str, _batch_size: usize, _progress: Option<Arc<dyn ProgressSink + Send + Sync>>) -> Result<()> {
         Err(anyhow!("Download feature is not enabled."))
     }
 }
 
 #[cfg(feature = "download")]
 impl BunkrDownloader {
-    pub async fn new() -> Result<Self> {
-        let client = Client::new();
+    /// Retries for each request after the first attempt, and restarts of a broken transfer.
+    const MAX_RETRIES: u32 = 3;
+
+    pub async fn new(config: &Config) -> Result<Self> {
+        let client = build_client(config)?;
+        let download_api_base = parse_base_url(config.download_api_base.as_deref().unwrap_or(DEFAULT_DOWNLOAD_API_BASE))?;
 
         let mut headers = header::HeaderMap::new();
-        headers.insert("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36".parse()?);
         headers.insert("Accept", "application/json, text/plain, */*".parse()?);
         headers.insert("Accept-Language", "en-US,en;q=0.9".parse()?);
         headers.insert("Content-Type", "application/json".parse()?);
@@ -101,45 +209,94 @@ impl BunkrDownloader {
         let orig_regex = OnceLock::new();
         orig_regex.get_or_init(|| Regex::new(r#"<h1 class="text-subs font-semibold text-base sm:text-lg truncate">([^<]+)</h1>"#).unwrap());
 
+        // Human readable size shown on the file page, e.g. "12.34 MB"
+        let size_regex = OnceLock::new();
+        size_regex.get_or_init(|| Regex::new(r">\s*(\d+(?:\.\d+)?)\s*(B|KB|MB|GB|TB)\s*<").unwrap());
+
+        let thumbnail_regex = OnceLock::new();
+        thumbnail_regex.get_or_init(|| Regex::new(r#"<meta property="og:image" content="([^"]+)""#).unwrap());
+
+        let album_title_regex = OnceLock::new();
+        album_title_regex.get_or_init(|| Regex::new(r#"<meta property="og:title" content="([^"]*)""#).unwrap());
+
         Ok(Self {
             client,
             headers,
+            download_api_base,
             album_files_regex,
             trailing_comma_regex,
             keys_regex,
             id_regex,
             orig_regex,
+            size_regex,
+            thumbnail_regex,
+            album_title_regex,
+            filename_template: config.download_template.clone().unwrap_or_else(|| "{name}".to_string()),
+            retry_jitter: config.retry_jitter.unwrap_or(true),
         })
     }
 
-    pub async fn get_files(&self, album_url: &str) -> Result<Vec<AlbumFile>> {
-        if album_url.contains("/a/") {
-            self.get_album_files(album_url).await
-        } else if album_url.contains("/f/") {
-            let file = self.get_single_file(album_url).await?;
-            Ok(vec![file])
+    /// Sends `cookie`, e.g. `session=...` copied from a logged-in browser, with the page and
+    /// download API requests, so private albums can be downloaded.
+    pub fn with_cookie(mut self, cookie: &str) -> Result<Self> {
+        let value = header::HeaderValue::from_str(cookie.trim())
+            .map_err(|_| anyhow!("Invalid cookie: it must be printable ASCII, like name=value; other=value"))?;
+        self.headers.insert(header::COOKIE, value);
+        Ok(self)
+    }
+
+    /// Fetches an album or file page, failing clearly when it's only shown to a logged-in user.
+    async fn fetch_page(&self, url: &str) -> Result<String> {
+        let mut request = self.client.get(url);
+        if let Some(cookie) = self.headers.get(header::COOKIE) {
+            request = request.header(header::COOKIE, cookie.clone());
+        }
+        let response = retry_with_backoff(|| async {
+            request.try_clone().ok_or_else(|| anyhow!("Request for {} can't be retried", url))?
+                .send().await.map_err(anyhow::Error::from)
+        }, Self::MAX_RETRIES, self.retry_jitter).await?;
+        let status = response.status();
+        if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN || response.url().path().contains("/login") {
+            return Err(self.authentication_required(url));
+        }
+        Ok(response.text().await?)
+    }
+
+    fn authentication_required(&self, url: &str) -> anyhow::Error {
+        if self.headers.contains_key(header::COOKIE) {
+            anyhow!("Authentication required for {}: the cookie was not accepted, it may have expired", url)
         } else {
-            Err(anyhow!("Unsupported URL: {}", album_url))
+            anyhow!("Authentication required for {}: pass --cookie or set BUNKR_COOKIE with the session cookie of a logged-in browser", url)
+        }
+    }
+
+    pub async fn get_files(&self, album_url: &str) -> Result<Vec<AlbumFile>> {
+        match parse_bunkr_url(album_url)? {
+            BunkrUrl::Album { .. } => self.get_album_files(album_url).await,
+            BunkrUrl::File { .. } => Ok(vec![self.get_single_file(album_url).await?]),
         }
     }
 
     async fn get_album_files(&self, album_url: &str) -> Result<Vec<AlbumFile>> {
         // Album URL
-        // Ensure the URL has advanced=1
-        let url = if album_url.contains("?") {
-            format!("{}&advanced=1", album_url)
-        } else {
-            format!("{}?advanced=1", album_url)
-        };
+        // Ensure the URL has advanced=1, added to the query so a #fragment doesn't swallow it
+        let mut url = reqwest::Url::parse(album_url.trim()).map_err(|e| anyhow!("Invalid URL '{}': {}", album_url, e))?;
+        if !url.query_pairs().any(|(key, _)| key == "advanced") {
+            url.query_pairs_mut().append_pair("advanced", "1");
+        }
 
-        let response = self.client.get(&url).send().await?;
-        let html = response.text().await?;
+        let html = self.fetch_page(url.as_str()).await?;
 
         // Regex to extract the window.albumFiles array
         let re = self.album_files_regex.get().unwrap();
 
-        let captures = re.captures(&html)
-            .ok_or_else(|| anyhow!("Could not find albumFiles in the page"))?;
+        let Some(captures) = re.captures(&html) else {
+            return Err(if is_login_page(&html) {
+                self.authentication_required(album_url)
+            } else {
+                anyhow!("Could not find albumFiles in the page")
+            });
+        };
 
         let js_array = &captures[1];
 
@@ -147,40 +304,72 @@ impl BunkrDownloader {
         let json_str = self.js_to_json(js_array)?;
 
         // Parse the JS-like array using json5 (supports single quotes, trailing commas, etc.)
-        let files: Vec<AlbumFile> = json5::from_str(&json_str)?;
+        let mut files: Vec<AlbumFile> = json5::from_str(&json_str)?;
+
+        let album = self.album_title_regex.get().unwrap().captures(&html)
+            .map(|c| unescape_html(c[1].trim()))
+            .unwrap_or_default();
+        for file in &mut files {
+            file.album = album.clone();
+        }
 
         Ok(files)
     }
 
     async fn get_single_file(&self, file_url: &str) -> Result<AlbumFile> {
         // Individual file URL
-        let response = self.client.get(file_url).send().await?;
-        let html = response.text().await?;
+        let html = self.fetch_page(file_url).await?;
 
         // Extract file id from <div id="fileTracker" data-file-id="...">
         let id_re = self.id_regex.get().unwrap();
-        let id: i64 = id_re.captures(&html)
-            .and_then(|c| c[1].parse().ok())
-            .ok_or_else(|| anyhow!("Could not find file id"))?;
+        let Some(id) = id_re.captures(&html).and_then(|c| c[1].parse::<i64>().ok()) else {
+            return Err(if is_login_page(&html) {
+                self.authentication_required(file_url)
+            } else {
+                anyhow!("Could not find file id")
+            });
+        };
 
         // Extract original filename from <h1 class="text-subs font-semibold text-base sm:text-lg truncate">
         let orig_re = self.orig_regex.get().unwrap();
         let original = orig_re.captures(&html)
-            .and_then(|c| Some(c[1].to_string()))
+            .map(|c| c[1].to_string())
             .ok_or_else(|| anyhow!("Could not find file name"))?;
 
-        // Create AlbumFile with extracted data, defaults for others
+        // Size is only shown rounded, good enough as a progress total when the download has no Content-Length
+        let size = self.size_regex.get().unwrap().captures(&html)
+            .and_then(|c| {
+                let value: f64 = c[1].parse().ok()?;
+                let unit: u64 = match &c[2] {
+                    "KB" => 1000,
+                    "MB" => 1000 * 1000,
+                    "GB" => 1000 * 1000 * 1000,
+                    "TB" => 1000 * 1000 * 1000 * 1000,
+                    _ => 1,
+                };
+                Some((value * unit as f64) as i64)
+            })
+            .unwrap_or(0);
+        let thumbnail = self.thumbnail_regex.get().unwrap().captures(&html)
+            .map(|c| c[1].to_string())
+            .unwrap_or_default();
+        let slug = file_url.trim_end_matches('/').rsplit('/').next().unwrap_or_default()
+            .split(['?', '#']).next().unwrap_or_default().to_string();
+        let extension = Path::new(&original).extension()
+            .map(|e| format!(".{}", e.to_string_lossy()))
+            .unwrap_or_default();
+        let file_type = mime_guess::from_path(&original).first_or_octet_stream().essence_str().to_string();
+
         let file = AlbumFile {
             id,
             name: original.clone(),
             original,
-            slug: "".to_string(),
-            file_type: "".to_string(),
-            extension: "".to_string(),
-            size: 0,
-            timestamp: "".to_string(),
-            thumbnail: "".to_string(),
-            cdn_endpoint: "".to_string(),
+            slug,
+            file_type,
+            extension,
+            size,
+            thumbnail,
+            ..Default::default()
         };
 
         Ok(file)
@@ -205,16 +394,56 @@ impl BunkrDownloader {
         Ok(json)
     }
 
-    pub async fn download_file(&self, file: &AlbumFile, output_dir: &str, ui_state: Option<Arc<Mutex<UIState>>>) -> Result<()> {
+    pub async fn download_file(&self, file: &AlbumFile, output_dir: &str, progress: Option<Arc<dyn ProgressSink + Send + Sync>>) -> Result<()> {
         Self::download_file_owned(
             self.client.clone(),
             self.headers.clone(),
+            self.download_api_base.clone(),
             Self::owned_album_file(file),
-            output_dir.to_string(),
-            ui_state,
+            Path::new(output_dir).join(self.file_path(file, 1)),
+            progress,
+            self.retry_jitter,
         ).await
     }
 
+    /// Direct, signed URL `file` can be fetched from, with its original name in the `n` parameter.
+    /// The signature expires, so resolve it shortly before downloading.
+    pub async fn resolve_download_url(&self, file: &AlbumFile) -> Result<String> {
+        Self::resolve_url(&self.client, &self.headers, &self.download_api_base, file, self.retry_jitter).await
+    }
+
+    async fn resolve_url(client: &Client, headers: &header::HeaderMap, download_api_base: &str, file: &AlbumFile, jitter: bool) -> Result<String> {
+        let api_url = format!("{}/api/_001_v2", download_api_base);
+        let body = serde_json::json!({ "id": file.id.to_string() });
+        let download_resp = Self::fetch_json::<DownloadResponse>(
+            client,
+            reqwest::Method::POST,
+            &api_url,
+            Some(headers),
+            Some(body),
+            "download_url",
+            jitter,
+        ).await?;
+
+        let token_url = format!("https://glb-apisign.cdn.cr/sign?path={}", download_resp.path);
+        let token_resp = Self::fetch_json::<TokenResponse>(
+            client,
+            reqwest::Method::GET,
+            &token_url,
+            None,
+            None,
+            "token",
+            jitter,
+        ).await?;
+
+        Ok(format!("{}{}?n={}&token={}&ex={}", download_resp.mediafiles, download_resp.path, download_resp.original, token_resp.token, token_resp.ex))
+    }
+
+    /// Path `file` is saved to relative to the output directory, `index` being its 1-based position in the batch.
+    pub fn file_path(&self, file: &AlbumFile, index: usize) -> PathBuf {
+        expand_filename_template(&self.filename_template, file, index)
+    }
+
     fn owned_album_file(file: &AlbumFile) -> AlbumFile {
         AlbumFile {
             id: file.id,
@@ -227,6 +456,7 @@ impl BunkrDownloader {
             timestamp: file.timestamp.clone(),
             thumbnail: file.thumbnail.clone(),
             cdn_endpoint: file.cdn_endpoint.clone(),
+            album: file.album.clone(),
         }
     }
 
@@ -237,12 +467,9 @@ impl BunkrDownloader {
         headers: Option<&header::HeaderMap>,
         body: Option<serde_json::Value>,
         label: &str,
+        jitter: bool,
     ) -> Result<T> {
-        let mut last_error = None;
-        for attempt in 0..3 {
-            if attempt > 0 {
-                tokio::time::sleep(Duration::from_millis(500 * (1 << attempt))).await;
-            }
+        let response = retry_with_backoff(|| async {
             let mut builder = client.request(method.clone(), url);
             if let Some(h) = headers {
                 builder = builder.headers(h.clone());
@@ -250,104 +477,76 @@ impl BunkrDownloader {
             if let Some(ref b) = body {
                 builder = builder.json(b);
             }
-            match builder.send().await {
-                Ok(resp) => {
-                    let status = resp.status();
-                    let body_text = resp.text().await?;
-                    if !status.is_success() {
-                        last_error = Some(anyhow!(
-                            "{} API returned status {}: {}",
-                            label, status, &body_text[..body_text.len().min(200)]
-                        ));
-                        continue;
-                    }
-                    match serde_json::from_str::<T>(&body_text) {
-                        Ok(val) => return Ok(val),
-                        Err(e) => {
-                            last_error = Some(anyhow!(
-                                "Failed to parse {} response (status {}): {} - body: {}",
-                                label, status, e, &body_text[..body_text.len().min(200)]
-                            ));
-                            continue;
-                        }
-                    }
-                }
-                Err(e) => {
-                    last_error = Some(anyhow!("{} request failed: {}", label, e));
-                    continue;
-                }
-            }
+            builder.send().await.map_err(|e| anyhow!("{} request failed: {}", label, e))
+        }, Self::MAX_RETRIES, jitter).await
+            .map_err(|e| Self::retries_exhausted(&format!("{} failed after {} retries", label, Self::MAX_RETRIES), e))?;
+        let status = response.status();
+        let body_text = response.text().await?;
+        if !status.is_success() {
+            return Err(HttpStatusError {
+                status: status.as_u16(),
+                message: format!("{} API returned status {}: {}", label, status, &body_text[..body_text.len().min(200)]),
+            }.into());
         }
-        Err(anyhow!(
-            "{} failed after 3 retries: {}",
-            label,
-            last_error.unwrap()
+        serde_json::from_str::<T>(&body_text).map_err(|e| anyhow!(
+            "Failed to parse {} response (status {}): {} - body: {}",
+            label, status, e, &body_text[..body_text.len().min(200)]
         ))
     }
 
     async fn download_file_owned(
         client: Client,
         headers: header::HeaderMap,
+        download_api_base: String,
         file: AlbumFile,
-        output_dir: String,
-        ui_state: Option<Arc<Mutex<UIState>>>,
+        file_path: PathBuf,
+        progress: Option<Arc<dyn ProgressSink + Send + Sync>>,
+        jitter: bool,
     ) -> Result<()> {
-        let file_path = Path::new(&output_dir).join(&file.original);
         if file_path.exists() {
             return Ok(());
         }
+        if let Some(parent) = file_path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
 
-        let api_url = "https://dl.bunkr.cr/api/_001_v2";
-
+        // Requests are retried on their own, this restarts transfers that broke off midway
         let mut last_error = None;
-        for attempt in 0..3 {
+        for attempt in 0..=Self::MAX_RETRIES {
             if attempt > 0 {
+                // A missing or forbidden file won't show up by starting over
+                if let Some(e) = last_error.take_if(|e| Self::is_client_error(e)) {
+                    return Err(e);
+                }
                 tokio::time::sleep(Duration::from_millis(1000 * (1 << attempt))).await;
+                // The file is written from the start again, so its progress starts over too
+                if let Some(ref progress) = progress {
+                    progress.on_start(&file.original, file.size as u64);
+                }
             }
 
-            let body = serde_json::json!({ "id": file.id.to_string() });
-
-            let download_resp = match Self::fetch_json::<DownloadResponse>(
-                &client,
-                reqwest::Method::POST,
-                api_url,
-                Some(&headers),
-                Some(body),
-                "download_url",
-            ).await {
-                Ok(r) => r,
+            let full_url = match Self::resolve_url(&client, &headers, &download_api_base, &file, jitter).await {
+                Ok(url) => url,
                 Err(e) => { last_error = Some(e); continue; }
             };
 
-            let token_url = format!("https://glb-apisign.cdn.cr/sign?path={}", download_resp.path);
-
-            let token_resp = match Self::fetch_json::<TokenResponse>(
-                &client,
-                reqwest::Method::GET,
-                &token_url,
-                None,
-                None,
-                "token",
-            ).await {
-                Ok(r) => r,
-                Err(e) => { last_error = Some(e); continue; }
-            };
-
-            let full_url = format!("{}{}?n={}&token={}&ex={}", download_resp.mediafiles, download_resp.path, download_resp.original, token_resp.token, token_resp.ex);
-
             let mut download_headers = header::HeaderMap::new();
-            download_headers.insert("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:151.0) Gecko/20100101 Firefox/151.0".parse()?);
             download_headers.insert("Accept", "*/*".parse()?);
             download_headers.insert("Accept-Language", "en-US,en;q=0.5".parse()?);
             download_headers.insert("Referer", "https://dl.bunkrr.cr/".parse()?);
 
-            let response = match client.get(&full_url).headers(download_headers).send().await {
+            let response = match retry_with_backoff(|| async {
+                client.get(&full_url).headers(download_headers.clone()).send().await.map_err(anyhow::Error::from)
+            }, Self::MAX_RETRIES, jitter).await {
                 Ok(r) => r,
-                Err(e) => { last_error = Some(anyhow!("{}", e)); continue; }
+                Err(e) => { last_error = Some(e); continue; }
             };
 
             if !response.status().is_success() {
-                last_error = Some(anyhow!("Failed to download file: {}", response.status()));
+                last_error = Some(HttpStatusError {
+                    status: response.status().as_u16(),
+                    message: format!("Failed to download file: {}", response.status()),
+                }.into());
                 continue;
             }
 
@@ -362,11 +561,10 @@ impl BunkrDownloader {
                             file_handle.write_all(&chunk).await?;
                             downloaded += chunk.len() as u64;
 
-                            if let Some(ref state) = ui_state {
-                                let mut state = state.lock().unwrap();
-                                let progress = if total_size > 0 { (downloaded as f64 / total_size as f64).min(1.0) } else { 0.0 };
-                                state.update_progress(&file.original, progress);
-                                state.add_processed_bytes(chunk.len() as u64);
+                            if let Some(ref progress) = progress {
+                                let fraction = if total_size > 0 { (downloaded as f64 / total_size as f64).min(1.0) } else { 0.0 };
+                                progress.on_progress(&file.original, fraction);
+                                progress.on_bytes(&file.original, chunk.len() as u64);
                             }
                         }
                         Ok(())
@@ -388,40 +586,75 @@ impl BunkrDownloader {
             }
         }
 
-        Err(anyhow!(
-            "Download failed after 3 retries: {}",
-            last_error.unwrap()
-        ))
+        Err(Self::retries_exhausted(&format!("Download failed after {} retries", Self::MAX_RETRIES), last_error.unwrap_or_else(|| anyhow!("no attempts were made"))))
+    }
+
+    fn is_client_error(e: &anyhow::Error) -> bool {
+        e.downcast_ref::<HttpStatusError>().is_some_and(|e| (400..500).contains(&e.status) && e.status != 429)
     }
 
-    pub async fn download_files(&self, files: Vec<AlbumFile>, output_dir: &str, batch_size: usize, ui_state: Option<Arc<Mutex<UIState>>>) -> Result<()> {
+    /// Prefixes the last error of a retry loop, keeping the HTTP status if it had one.
+    fn retries_exhausted(prefix: &str, last_error: anyhow::Error) -> anyhow::Error {
+        let message = format!("{}: {}", prefix, last_error);
+        match last_error.downcast_ref::<HttpStatusError>() {
+            Some(e) => HttpStatusError { status: e.status, message }.into(),
+            None => anyhow!(message),
+        }
+    }
+
+    pub async fn download_files(&self, files: Vec<AlbumFile>, output_dir: &str, batch_size: usize, progress: Option<Arc<dyn ProgressSink + Send + Sync>>) -> Result<()> {
         let batch_size = batch_size.max(1);
-        let output_dir = output_dir.to_string();
+        let output_dir = Path::new(output_dir);
         let client = self.client.clone();
         let headers = self.headers.clone();
+        let download_api_base = self.download_api_base.clone();
+
+        std::fs::create_dir_all(output_dir)?;
+        let mut manifest = DownloadManifest::load(output_dir);
+        let mut pending = Vec::new();
+        for (index, file) in files.into_iter().enumerate() {
+            let relative = self.file_path(&file, index + 1);
+            let file_path = output_dir.join(&relative);
+            if manifest.is_complete(&file, &file_path, &relative) {
+                if let Some(ref progress) = progress {
+                    progress.on_skip(&file.original, "already downloaded");
+                }
+                continue;
+            }
+            // Left over from an interrupted run, or changed since it finished
+            if manifest.files.contains_key(&file.id.to_string()) && file_path.exists() {
+                if manifest.is_partial(&file) {
+                    debug!("Restarting partial download of {}", file_path.display());
+                }
+                let _ = std::fs::remove_file(&file_path);
+            }
+            manifest.record(&file, &relative, file.size as u64, false);
+            pending.push((file, file_path, relative));
+        }
+        manifest.save_or_warn();
 
-        let mut files_iter = files.into_iter();
+        let mut files_iter = pending.into_iter();
         let mut join_set = JoinSet::new();
 
-        let mut spawn_next = |join_set: &mut JoinSet<(AlbumFile, Result<()>)>| {
-            let Some(file) = files_iter.next() else {
+        let mut spawn_next = |join_set: &mut JoinSet<(AlbumFile, PathBuf, PathBuf, Result<()>)>| {
+            let Some((file, file_path, relative)) = files_iter.next() else {
                 return;
             };
 
             let client = client.clone();
             let headers = headers.clone();
-            let output_dir = output_dir.clone();
-            let ui_state = ui_state.clone();
+            let download_api_base = download_api_base.clone();
+            let progress = progress.clone();
+            let jitter = self.retry_jitter;
 
             join_set.spawn(async move {
-                if let Some(ref state) = ui_state {
-                    let mut state = state.lock().unwrap();
-                    state.add_current_operation(file.original.clone(), 0.0, file.size as u64);
+                if let Some(ref progress) = progress {
+                    progress.on_start(&file.original, file.size as u64);
                 }
 
                 let file_for_result = BunkrDownloader::owned_album_file(&file);
-                let result = BunkrDownloader::download_file_owned(client, headers, file, output_dir, ui_state.clone()).await;
-                (file_for_result, result)
+                let result = BunkrDownloader::download_file_owned(client, headers, download_api_base, file, file_path.clone(), progress.clone(), jitter).await;
+                (file_for_result, file_path, relative, result)
             });
         };
 
@@ -430,24 +663,26 @@ impl BunkrDownloader {
         }
 
         while let Some(result) = join_set.join_next().await {
-            if let Ok((file, result)) = result {
+            if let Ok((file, file_path, relative, result)) = result {
                 match result {
                     Ok(_) => {
-                        if let Some(ref state) = ui_state {
-                            let mut state = state.lock().unwrap();
-                            state.remove_current_operation(&file.original, None);
+                        let size = std::fs::metadata(&file_path).map(|m| m.len()).unwrap_or(0);
+                        manifest.record(&file, &relative, size, true);
+                        manifest.save_or_warn();
+                        if let Some(ref progress) = progress {
+                            progress.on_complete(&file.original, None);
                         }
                     }
                     Err(e) => {
-                        if let Some(ref state) = ui_state {
-                            let mut state = state.lock().unwrap();
+                        if let Some(ref progress) = progress {
                             let info = FailedOperationInfo {
                                 path: file.original.clone(),
                                 error: e.to_string(),
                                 file_size: file.size as u64,
-                                status_code: None,
+                                status_code: e.downcast_ref::<HttpStatusError>().map(|e| e.status),
+                                source: None,
                             };
-                            state.add_failed_operation(file.original.clone(), info);
+                            progress.on_fail(&file.original, &info);
                         }
                     }
                 }
@@ -458,4 +693,22 @@ impl BunkrDownloader {
 
         Ok(())
     }
+}
+
+/// Whether a page that should have listed files is a login form instead.
+#[cfg(feature = "download")]
+fn is_login_page(html: &str) -> bool {
+    html.contains(r#"type="password""#) || html.contains(r#"action="/login""#)
+}
+
+/// Decodes the entities an HTML attribute value can contain, e.g. in an album title.
+#[cfg(feature = "download")]
+fn unescape_html(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
 }
\ No newline at end of file
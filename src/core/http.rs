@@ -0,0 +1,148 @@
+use anyhow::Result;
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::warn;
+
+/// Longest `Retry-After` wait honored, so a misbehaving server can't stall a batch for hours.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(300);
+
+/// Sends the request built by `f`, retrying transport errors and transient server errors with
+/// exponential backoff starting at one second, up to `max_retries` times.
+pub(crate) async fn retry_with_backoff<F, Fut>(f: F, max_retries: u32, jitter: bool) -> Result<reqwest::Response>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<reqwest::Response>>,
+{
+    retry_counted(f, max_retries, jitter, &mut 0).await
+}
+
+/// Same as `retry_with_backoff`, adding each retry it makes to `retries`.
+pub(crate) async fn retry_counted<F, Fut>(mut f: F, max_retries: u32, jitter: bool, retries: &mut u32) -> Result<reqwest::Response>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<reqwest::Response>>,
+{
+    let mut delay = Duration::from_secs(1);
+    for attempt in 0..=max_retries {
+        match f().await {
+            Ok(response) => {
+                // Transient server errors are retried, anything else (including 4xx) is returned as-is
+                let status = response.status();
+                if attempt == max_retries || !is_retryable_status(status) {
+                    return Ok(response);
+                }
+                // Prefer the server's Retry-After hint over our own backoff
+                let wait = retry_after(&response).unwrap_or_else(|| backoff_delay(delay, jitter));
+                warn!("Attempt {} returned status {}, retrying in {:?}", attempt + 1, status, wait);
+                *retries += 1;
+                sleep(wait).await;
+                delay = delay.saturating_mul(2);
+            }
+            Err(e) => {
+                if attempt == max_retries {
+                    return Err(e);
+                }
+                let wait = backoff_delay(delay, jitter);
+                warn!("Attempt {} failed: {}, retrying in {:?}", attempt + 1, e, wait);
+                *retries += 1;
+                sleep(wait).await;
+                delay = delay.saturating_mul(2);
+            }
+        }
+    }
+    unreachable!()
+}
+
+pub(crate) fn is_retryable_status(status: reqwest::StatusCode) -> bool {
+    matches!(status.as_u16(), 429 | 500 | 502 | 503 | 504)
+}
+
+/// How long to wait before a retry. With jitter it's a random duration up to `delay` ("full
+/// jitter"), so requests that failed together don't all retry at the same moment.
+pub(crate) fn backoff_delay(delay: Duration, jitter: bool) -> Duration {
+    if jitter {
+        delay.mul_f64(rand::random::<f64>())
+    } else {
+        delay
+    }
+}
+
+/// Parses a `Retry-After` header given either as delay-seconds or as an HTTP-date.
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    let value = response.headers().get(reqwest::header::RETRY_AFTER)?.to_str().ok()?.trim();
+    let wait = if let Ok(secs) = value.parse::<u64>() {
+        Duration::from_secs(secs)
+    } else {
+        let date = httpdate::parse_http_date(value).ok()?;
+        date.duration_since(std::time::SystemTime::now()).unwrap_or(Duration::ZERO)
+    };
+    Some(wait.min(MAX_RETRY_AFTER))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    fn response_with_retry_after(value: &str) -> reqwest::Response {
+        http::Response::builder()
+            .status(503)
+            .header(reqwest::header::RETRY_AFTER, value)
+            .body("")
+            .unwrap()
+            .into()
+    }
+
+    #[test]
+    fn jittered_delay_stays_within_the_backoff() {
+        let delay = Duration::from_secs(4);
+        for _ in 0..1000 {
+            assert!(backoff_delay(delay, true) <= delay);
+        }
+    }
+
+    #[test]
+    fn delay_without_jitter_is_the_backoff() {
+        assert_eq!(backoff_delay(Duration::from_secs(4), false), Duration::from_secs(4));
+    }
+
+    #[test]
+    fn retry_after_in_seconds() {
+        assert_eq!(retry_after(&response_with_retry_after("12")), Some(Duration::from_secs(12)));
+        assert_eq!(retry_after(&response_with_retry_after(" 0 ")), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn retry_after_as_http_date() {
+        let date = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(60));
+        let wait = retry_after(&response_with_retry_after(&date)).unwrap();
+        assert!(wait > Duration::from_secs(55) && wait <= Duration::from_secs(60), "{:?}", wait);
+
+        let past = httpdate::fmt_http_date(SystemTime::now() - Duration::from_secs(60));
+        assert_eq!(retry_after(&response_with_retry_after(&past)), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn retry_after_is_clamped() {
+        assert_eq!(retry_after(&response_with_retry_after("86400")), Some(MAX_RETRY_AFTER));
+        let date = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(3600));
+        assert_eq!(retry_after(&response_with_retry_after(&date)), Some(MAX_RETRY_AFTER));
+    }
+
+    #[test]
+    fn invalid_or_missing_retry_after_is_ignored() {
+        assert_eq!(retry_after(&response_with_retry_after("soon")), None);
+        let response: reqwest::Response = http::Response::builder().status(503).body("").unwrap().into();
+        assert_eq!(retry_after(&response), None);
+    }
+
+    #[test]
+    fn only_transient_statuses_are_retried() {
+        for status in [429, 500, 502, 503, 504] {
+            assert!(is_retryable_status(reqwest::StatusCode::from_u16(status).unwrap()), "{}", status);
+        }
+        for status in [200, 400, 401, 403, 404, 413, 501] {
+            assert!(!is_retryable_status(reqwest::StatusCode::from_u16(status).unwrap()), "{}", status);
+        }
+    }
+}
@@ -1,4 +1,9 @@
 pub mod uploader;
+pub mod backend;
+pub mod http;
+pub mod cache;
 pub mod downloader;
 pub mod types;
+pub mod rate_limiter;
+pub mod progress;
 pub mod utils;
\ No newline at end of file
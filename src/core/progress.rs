@@ -0,0 +1,66 @@
+use crate::core::types::FailedOperationInfo;
+use tokio::sync::mpsc::UnboundedReceiver;
+
+/// Receives progress notifications from the uploader and downloader. The built-in TUI implements
+/// this for `Mutex<UIState>`, library consumers can plug in their own implementation.
+pub trait ProgressSink {
+    fn on_start(&self, path: &str, size: u64);
+    fn on_progress(&self, path: &str, progress: f64);
+    fn on_complete(&self, path: &str, url: Option<&str>);
+    fn on_fail(&self, path: &str, info: &FailedOperationInfo);
+
+    /// Bytes transferred for `path` since the previous call, used for throughput.
+    fn on_bytes(&self, _path: &str, _bytes: u64) {}
+
+    /// Called before preprocessing starts on `path`.
+    fn on_preprocess(&self, _path: &str, _size: u64) {}
+
+    /// Fraction (0.0 to 1.0) of the preprocessing of `path` done so far, for steps that can tell, like video splitting.
+    fn on_preprocess_progress(&self, _path: &str, _progress: f64) {}
+
+    /// Called once preprocessing finished with the files that will actually be uploaded for `path`.
+    fn on_preprocessed(&self, _path: &str, _files: &[String]) {}
+
+    /// Called instead of `on_start` for a file that won't be uploaded, e.g. because it's empty.
+    fn on_skip(&self, _path: &str, _reason: &str) {}
+
+    /// Upload age chosen for `path`, overriding the configured default. Asked right before its upload starts.
+    fn upload_age(&self, _path: &str) -> Option<i64> {
+        None
+    }
+
+    /// Hands the uploader a receiver of paths the user asked to retry. Called once per batch.
+    fn take_retry_receiver(&self) -> Option<UnboundedReceiver<String>> {
+        None
+    }
+}
+
+/// Prints one line per started, finished and failed file. Used instead of the TUI when
+/// stdout isn't a terminal, e.g. when piped or run from cron.
+pub struct LineProgress;
+
+impl ProgressSink for LineProgress {
+    fn on_start(&self, path: &str, size: u64) {
+        println!("Started {} ({} bytes)", path, size);
+    }
+
+    fn on_progress(&self, _path: &str, _progress: f64) {}
+
+    fn on_complete(&self, path: &str, url: Option<&str>) {
+        match url {
+            Some(url) => println!("Done {}: {}", path, url),
+            None => println!("Done {}", path),
+        }
+    }
+
+    fn on_skip(&self, path: &str, reason: &str) {
+        println!("Skipped {}: {}", path, reason);
+    }
+
+    fn on_fail(&self, path: &str, info: &FailedOperationInfo) {
+        match info.status_code {
+            Some(status) => println!("Failed {}: {} (status {})", path, info.error, status),
+            None => println!("Failed {}: {}", path, info.error),
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use std::sync::{Arc, Mutex, Weak};
+use tokio::sync::Semaphore;
+use tokio::time::{interval, sleep, Duration, Instant};
+
+/// Token bucket shared by every upload task so the total request rate stays under a cap,
+/// independent of how many files or chunks are in flight.
+pub struct RateLimiter {
+    permits: Arc<Semaphore>,
+}
+
+impl RateLimiter {
+    pub fn new(requests_per_second: u32) -> Self {
+        let capacity = requests_per_second.max(1);
+        let permits = Arc::new(Semaphore::new(capacity as usize));
+        tokio::spawn(Self::refill(Arc::downgrade(&permits), capacity));
+        Self { permits }
+    }
+
+    // Adds one permit every 1/capacity seconds until the bucket is full, stops once the limiter is dropped
+    async fn refill(permits: Weak<Semaphore>, capacity: u32) {
+        let mut ticker = interval(Duration::from_secs(1) / capacity);
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            let Some(permits) = permits.upgrade() else {
+                break;
+            };
+            if permits.available_permits() < capacity as usize {
+                permits.add_permits(1);
+            }
+        }
+    }
+
+    pub async fn acquire(&self) {
+        if let Ok(permit) = self.permits.acquire().await {
+            permit.forget();
+        }
+    }
+}
+
+/// Shared byte budget capping the combined upload speed of every task. Callers take bytes
+/// before sending them and sleep off any overdraft, so the cap holds over roughly a second
+/// rather than exactly at every instant.
+pub struct BandwidthLimiter {
+    bytes_per_second: u64,
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    available: f64,
+    updated: Instant,
+}
+
+impl BandwidthLimiter {
+    pub fn new(bytes_per_second: u64) -> Self {
+        let bytes_per_second = bytes_per_second.max(1);
+        Self {
+            bytes_per_second,
+            bucket: Mutex::new(Bucket { available: bytes_per_second as f64, updated: Instant::now() }),
+        }
+    }
+
+    pub async fn consume(&self, bytes: u64) {
+        let wait = {
+            let mut bucket = self.bucket.lock().unwrap();
+            let now = Instant::now();
+            let rate = self.bytes_per_second as f64;
+            // Refill for the time since the last call, allowing at most one second of burst
+            bucket.available = (bucket.available + now.duration_since(bucket.updated).as_secs_f64() * rate).min(rate);
+            bucket.updated = now;
+            bucket.available -= bytes as f64;
+            if bucket.available < 0.0 {
+                Duration::from_secs_f64(-bucket.available / rate)
+            } else {
+                Duration::ZERO
+            }
+        };
+        if !wait.is_zero() {
+            sleep(wait).await;
+        }
+    }
+}
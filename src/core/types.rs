@@ -1,6 +1,6 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone)]
 #[allow(dead_code)]
 pub struct UserPermissions {
     pub user: bool,
@@ -11,7 +11,7 @@ pub struct UserPermissions {
     pub superadmin: bool,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone)]
 #[allow(dead_code)]
 #[allow(non_snake_case)]
 pub struct VerifyResponse {
@@ -43,24 +43,178 @@ pub struct UploadedFile {
 }
 
 #[derive(Clone, Debug)]
+pub struct UploadOutcome {
+    pub path: String,
+    pub url: String,
+    pub file_size: u64,
+    /// Hex-encoded SHA-256 of the uploaded bytes, computed while the file is read.
+    pub sha256: Option<String>,
+    /// Requests that had to be retried, plus any switches to a fresh upload node.
+    pub retries: u32,
+}
+
+/// Order in which `upload_files` starts the files of a batch.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UploadSchedule {
+    /// The order the files were given in.
+    #[default]
+    Input,
+    LargestFirst,
+    /// Gives quick feedback, the big files run last.
+    SmallestFirst,
+    /// Alternates between the largest and smallest remaining files to keep the connection busy.
+    Interleaved,
+}
+
+impl UploadSchedule {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UploadSchedule::Input => "input",
+            UploadSchedule::LargestFirst => "largest-first",
+            UploadSchedule::SmallestFirst => "smallest-first",
+            UploadSchedule::Interleaved => "interleaved",
+        }
+    }
+}
+
+impl std::fmt::Display for UploadSchedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for UploadSchedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "input" => Ok(UploadSchedule::Input),
+            "largest-first" => Ok(UploadSchedule::LargestFirst),
+            "smallest-first" => Ok(UploadSchedule::SmallestFirst),
+            "interleaved" => Ok(UploadSchedule::Interleaved),
+            _ => Err(format!("unknown schedule '{}', expected input, largest-first, smallest-first or interleaved", s)),
+        }
+    }
+}
+
+/// How an upload is checked against the stored file once the server returned its URL.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VerifyMode {
+    /// `HEAD` the URL and compare `Content-Length` with the local size.
+    Size,
+    /// Also fetch the first 64 KiB with a range request and compare them with the local file.
+    Hash,
+}
+
+impl VerifyMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VerifyMode::Size => "size",
+            VerifyMode::Hash => "hash",
+        }
+    }
+}
+
+impl std::fmt::Display for VerifyMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for VerifyMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "size" => Ok(VerifyMode::Size),
+            "hash" => Ok(VerifyMode::Hash),
+            _ => Err(format!("unknown verify mode '{}', expected size or hash", s)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct FailedOperationInfo {
     pub path: String,
     pub error: String,
     pub file_size: u64,
     pub status_code: Option<u16>,
+    /// File `path` was split or converted from, which is what has to be uploaded again to retry it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
 }
 
-#[cfg(not(feature = "ui"))]
-#[derive(Clone)]
-pub struct UIState;
+/// What happened to one file passed to `BunkrUploader::upload`.
+#[derive(Clone, Debug)]
+pub enum FileUploadOutcome {
+    Uploaded(UploadOutcome),
+    Failed(FailedOperationInfo),
+}
 
-#[derive(Debug, Deserialize)]
+impl FileUploadOutcome {
+    pub fn path(&self) -> &str {
+        match self {
+            FileUploadOutcome::Uploaded(outcome) => &outcome.path,
+            FileUploadOutcome::Failed(info) => &info.path,
+        }
+    }
+
+    pub fn url(&self) -> Option<&str> {
+        match self {
+            FileUploadOutcome::Uploaded(outcome) => Some(&outcome.url),
+            FileUploadOutcome::Failed(_) => None,
+        }
+    }
+}
+
+/// A request that got a non-success HTTP response. Kept as the error type so callers can
+/// tell HTTP failures apart from network and IO errors with `downcast_ref`.
+#[derive(Debug)]
+pub struct HttpStatusError {
+    pub status: u16,
+    pub message: String,
+}
+
+impl std::fmt::Display for HttpStatusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for HttpStatusError {}
+
+/// Progress notifications for library consumers that don't use the built-in UI.
+#[derive(Clone, Debug)]
+pub enum ProgressEvent {
+    Started { path: String, size: u64 },
+    Chunk { path: String, uploaded: u64, total: u64 },
+    /// `retries` counts the requests retried while uploading this file.
+    Completed { path: String, url: Option<String>, retries: u32 },
+    Failed { path: String, info: FailedOperationInfo },
+    Skipped { path: String, reason: String },
+}
+
+/// What a Bunkr page URL points to, see `parse_bunkr_url`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BunkrUrl {
+    /// An album page (`/a/<slug>`).
+    Album { slug: String },
+    /// A single file page (`/f/<slug>`).
+    File { slug: String },
+}
+
+#[derive(Debug, Clone, Deserialize)]
 pub struct Album {
     pub id: i64,
     pub name: String,
 }
 
-#[derive(Debug, Deserialize)]
+/// A file in an album, from the public album page or the dashboard API. Fields one of them
+/// doesn't send are left empty.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
 pub struct AlbumFile {
     pub id: i64,
     pub name: String,
@@ -70,10 +224,23 @@ pub struct AlbumFile {
     pub file_type: String,
     pub extension: String,
     pub size: i64,
+    #[serde(deserialize_with = "string_or_number")]
     pub timestamp: String,
     pub thumbnail: String,
     #[serde(rename = "cdnEndpoint")]
     pub cdn_endpoint: String,
+    /// Title of the album page the file was listed on, empty for single files and the dashboard API.
+    #[serde(skip)]
+    pub album: String,
+}
+
+/// The dashboard sends timestamps as numbers, the album page as strings.
+fn string_or_number<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    Ok(match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::String(s) => s,
+        serde_json::Value::Null => String::new(),
+        other => other.to_string(),
+    })
 }
 
 #[derive(Debug, Deserialize)]
@@ -1,64 +1,204 @@
-use crate::{config::bunkr_config::BunkrConfig, config::config::Config, preprocess::preprocess::cleanup_preprocess, core::types::*, core::utils::parse_size};
-#[cfg(feature = "ui")]
-use crate::ui::ui::{UIState, OperationStatus};
-#[cfg(not(feature = "ui"))]
-use crate::core::types::UIState;
+use crate::{config::bunkr_config::BunkrConfig, config::config::{Config, DEFAULT_BASE_URL, DEFAULT_MAX_SIZE_MARGIN}, preprocess::preprocess::PreprocessGuard, core::types::*, core::utils::{parse_size, parse_base_url, build_client, normalize_token, sanitize_filename}, core::rate_limiter::{BandwidthLimiter, RateLimiter}, core::progress::ProgressSink, core::http::{retry_counted, retry_with_backoff}, core::backend::{BunkrBackend, LocalFsBackend, UploadBackend, UploadPart}};
 use anyhow::{Result, anyhow};
 use mime_guess::from_path;
-use reqwest::{Client, multipart, Body};
+use reqwest::Client;
+use bytes::Bytes;
+use futures_util::{stream, stream::BoxStream, Stream, StreamExt};
 use serde_json::json;
-use std::{path::Path, sync::{Arc, Mutex}};
-use tokio::time::{sleep, Duration};
+use sha2::{Digest, Sha256};
+use std::{collections::{HashMap, HashSet, VecDeque}, path::Path, sync::{Arc, Mutex}};
+use tracing::{debug, error, warn};
 use tokio::fs::File as TokioFile;
 use tokio::io::AsyncReadExt;
+use tokio::sync::mpsc;
 use tokio::task::JoinSet;
-use tokio_util::io::ReaderStream;
+use tokio_util::io::{InspectReader, ReaderStream};
 use uuid::Uuid;
 
+/// Options for `BunkrUploader::upload`. The defaults upload one file at a time, outside any album.
+#[derive(Default)]
+pub struct UploadOptions {
+    pub album_id: Option<i64>,
+    /// Files uploaded at once, at least 1.
+    pub batch_size: usize,
+    /// Age for temporary uploads, see `validate_upload_age`. Overrides `config.default_upload_age`.
+    pub upload_age: Option<i64>,
+    pub progress: Option<Arc<dyn ProgressSink + Send + Sync>>,
+    /// Settings such as `fail_fast` or `upload_schedule`; `Config::default()` when unset.
+    pub config: Option<Config>,
+}
+
+#[derive(Clone)]
 pub struct BunkrUploader {
     client: Client,
+    api_base: String,
     headers: reqwest::header::HeaderMap,
     upload_url: String,
     max_file_size: u64,
     chunk_size: u64,
+    send_file_hash: bool,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    bandwidth_limiter: Option<Arc<BandwidthLimiter>>,
+    progress_tx: Option<mpsc::Sender<ProgressEvent>>,
+    account: Arc<VerifyResponse>,
+    strip_tags: Option<bool>,
+    server_config: Arc<BunkrConfig>,
+    /// Names sent so far in this batch, set when duplicate names should be renamed.
+    used_names: Option<Arc<Mutex<HashSet<String>>>>,
+    /// Name sent to the server for the file being uploaded, when it differs from the file on disk.
+    upload_name: Option<String>,
+    /// File the one being uploaded was split or converted from, recorded in its failures.
+    source_path: Option<String>,
+    /// Names to upload files under instead of their own, keyed by path.
+    display_names: Arc<HashMap<String, String>>,
+    /// Where files and chunks are actually sent.
+    backend: Arc<dyn UploadBackend>,
+    /// Albums from the last `get_albums` call made for a name lookup, shared by clones.
+    albums_cache: Arc<tokio::sync::Mutex<Option<Vec<Album>>>>,
+    /// Randomize retry delays, see `http::backoff_delay`.
+    retry_jitter: bool,
 }
 
 impl BunkrUploader {
     const NODE_REFRESH_RETRIES: u32 = 6;
+    const PACE_PIECE_SIZE: usize = 64 * 1024;
+    /// Each chunk is read into memory whole, so a bad server or config value can't ask for gigabytes.
+    const MAX_CHUNK_SIZE: u64 = 256 * 1024 * 1024;
+
+    /// Uses the configured chunk size when it parses, clamped to the server's max, otherwise the server default.
+    /// Either way the result is capped at `MAX_CHUNK_SIZE`.
+    fn resolve_chunk_size(requested: Option<&str>, server_default: &str, server_max: &str) -> Result<u64> {
+        let default = parse_size(server_default)?;
+        let size = match requested.map(parse_size) {
+            None => default,
+            Some(Ok(size)) if size > 0 => size.min(parse_size(server_max).unwrap_or(default)),
+            Some(_) => {
+                warn!("Ignoring invalid chunk_size '{}', using server default {}", requested.unwrap_or_default(), server_default);
+                default
+            }
+        };
+        if size > Self::MAX_CHUNK_SIZE {
+            warn!("Chunk size of {} bytes is too large to buffer, using {} bytes", size, Self::MAX_CHUNK_SIZE);
+            return Ok(Self::MAX_CHUNK_SIZE);
+        }
+        Ok(size)
+    }
+
+    /// Largest file to upload whole: the server's `maxSize` less the margin, or the configured
+    /// `max_file_size` when that's lower. The margin must be in `(0, 1]`.
+    fn resolve_max_file_size(server_max: &str, margin: Option<f64>, requested: Option<&str>) -> Result<u64> {
+        // Leave some of the server's limit as headroom for request overhead
+        let margin = margin.unwrap_or(DEFAULT_MAX_SIZE_MARGIN);
+        if !(margin > 0.0 && margin <= 1.0) {
+            return Err(anyhow!("max_size_margin must be above 0 and at most 1, got {}", margin));
+        }
+        let server_max_file_size = (parse_size(server_max)? as f64 * margin) as u64;
+        match requested {
+            Some(size) => {
+                let size = parse_size(size)?;
+                if size > server_max_file_size {
+                    warn!("max_file_size {} is above the server limit, using {} bytes instead", size, server_max_file_size);
+                }
+                Ok(size.min(server_max_file_size))
+            }
+            None => Ok(server_max_file_size),
+        }
+    }
+
+    async fn emit(&self, event: ProgressEvent) {
+        if let Some(tx) = &self.progress_tx {
+            // A dropped receiver just means nobody is listening anymore
+            let _ = tx.send(event).await;
+        }
+    }
+
+    async fn report_failure(&self, progress: &Option<Arc<dyn ProgressSink + Send + Sync>>, failure: &FailedOperationInfo) {
+        if let Some(progress) = progress {
+            progress.on_fail(&failure.path, failure);
+        }
+        self.emit(ProgressEvent::Failed { path: failure.path.clone(), info: failure.clone() }).await;
+    }
+
+    /// Records a file whose upload task ended without a result of its own, so it still counts as failed.
+    async fn report_task_failure(&self, progress: &Option<Arc<dyn ProgressSink + Send + Sync>>, path: String, error: String) -> FailedOperationInfo {
+        let failure = FailedOperationInfo {
+            file_size: std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0),
+            path,
+            error,
+            status_code: None,
+            source: None,
+        };
+        self.report_failure(progress, &failure).await;
+        failure
+    }
+
+    async fn report_skip(&self, progress: &Option<Arc<dyn ProgressSink + Send + Sync>>, path: &str, reason: &str) {
+        if let Some(progress) = progress {
+            progress.on_skip(path, reason);
+        }
+        self.emit(ProgressEvent::Skipped { path: path.to_string(), reason: reason.to_string() }).await;
+    }
+
+    /// Returns the failure as the upload result, reporting it only when it's the final attempt.
+    async fn fail(&self, progress: &Option<Arc<dyn ProgressSink + Send + Sync>>, failure: FailedOperationInfo, record_failure: bool) -> Result<(Option<UploadOutcome>, Vec<FailedOperationInfo>)> {
+        let failure = FailedOperationInfo { source: failure.source.or_else(|| self.source_path.clone()), ..failure };
+        if record_failure {
+            self.report_failure(progress, &failure).await;
+        }
+        Ok((None, vec![failure]))
+    }
 
-    async fn retry_with_backoff<F, Fut>(mut f: F, max_retries: u32) -> Result<reqwest::Response, anyhow::Error>
+    /// Wraps an upload body so it's sent no faster than the bandwidth limit, when one is set.
+    fn paced_body<S>(&self, body: S) -> BoxStream<'static, std::io::Result<Bytes>>
     where
-        F: FnMut() -> Fut,
-        Fut: std::future::Future<Output = Result<reqwest::Response, anyhow::Error>>,
+        S: Stream<Item = std::io::Result<Bytes>> + Send + 'static,
     {
-        let mut delay = Duration::from_secs(1);
-        for attempt in 0..=max_retries {
-            match f().await {
-                Ok(response) => return Ok(response),
-                Err(e) => {
-                    if attempt == max_retries {
-                        return Err(e);
+        match self.bandwidth_limiter.clone() {
+            Some(limiter) => body.then(move |item| {
+                let limiter = limiter.clone();
+                async move {
+                    if let Ok(bytes) = &item {
+                        limiter.consume(bytes.len() as u64).await;
                     }
-                    eprintln!("Attempt {} failed: {}, retrying in {:?}", attempt + 1, e, delay);
-                    sleep(delay).await;
-                    delay = delay.saturating_mul(2);
+                    item
                 }
-            }
+            }).boxed(),
+            None => body.boxed(),
+        }
+    }
+
+    /// Splits `data` into small pieces so a bandwidth limit paces it instead of stalling between sends.
+    fn pace_pieces(data: &Bytes) -> Vec<std::io::Result<Bytes>> {
+        (0..data.len())
+            .step_by(Self::PACE_PIECE_SIZE)
+            .map(|start| Ok(data.slice(start..(start + Self::PACE_PIECE_SIZE).min(data.len()))))
+            .collect()
+    }
+
+    async fn throttle(&self) {
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.acquire().await;
         }
-        unreachable!()
+    }
+
+    fn is_client_error(status_code: Option<u16>) -> bool {
+        status_code.is_some_and(|code| (400..500).contains(&code) && code != 429)
     }
 
     async fn fetch_upload_url(
         client: &Client,
+        api_base: &str,
         headers: &reqwest::header::HeaderMap,
+        jitter: bool,
     ) -> Result<String> {
-        let response = Self::retry_with_backoff(|| async {
+        debug!("Fetching upload node from {}/api/node", api_base);
+        let response = retry_with_backoff(|| async {
             client
-                .get("https://dash.bunkr.cr/api/node")
+                .get(format!("{}/api/node", api_base))
                 .headers(headers.clone())
                 .send().await
                 .map_err(anyhow::Error::from)
-        }, 5).await?;
+        }, 5, jitter).await?;
         let status = response.status();
         let text = response.text().await?;
         if !status.is_success() {
@@ -67,8 +207,8 @@ impl BunkrUploader {
         let node: NodeResponse = match serde_json::from_str(&text) {
             Ok(r) => r,
             Err(e) => {
-                eprintln!("Failed to parse node response: {}", e);
-                eprintln!("Response: {}", text);
+                error!("Failed to parse node response: {}", e);
+                debug!("Response: {}", text);
                 return Err(anyhow!("JSON parsing error: {}", e));
             }
         };
@@ -78,16 +218,20 @@ impl BunkrUploader {
         Ok(node.url)
     }
 
-    pub async fn new(token: String) -> Result<Self> {
-        let client = Client::new();
+    pub async fn new(token: String, config: &Config) -> Result<Self> {
+        let token = normalize_token(&token)?;
+        let client = build_client(config)?;
+        let jitter = config.retry_jitter.unwrap_or(true);
+        let api_base = parse_base_url(config.base_url.as_deref().unwrap_or(DEFAULT_BASE_URL))?;
 
-        let response = Self::retry_with_backoff(|| async {
+        debug!("Verifying token at {}/api/tokens/verify", api_base);
+        let response = retry_with_backoff(|| async {
             client
-                .post("https://dash.bunkr.cr/api/tokens/verify")
+                .post(format!("{}/api/tokens/verify", api_base))
                 .form(&[("token", token.clone())])
                 .send().await
                 .map_err(anyhow::Error::from)
-        }, 5).await?;
+        }, 5, jitter).await?;
         let status = response.status();
         let text = response.text().await?;
         if !status.is_success() {
@@ -96,8 +240,8 @@ impl BunkrUploader {
         let verify: VerifyResponse = match serde_json::from_str(&text) {
             Ok(r) => r,
             Err(e) => {
-                eprintln!("Failed to parse token verification response: {}", e);
-                eprintln!("Response: {}", text);
+                error!("Failed to parse token verification response: {}", e);
+                debug!("Response: {}", text);
                 return Err(anyhow!("JSON parsing error: {}", e));
             }
         };
@@ -105,94 +249,252 @@ impl BunkrUploader {
             return Err(anyhow!("Invalid API token"));
         }
 
-        let response = Self::retry_with_backoff(|| async {
+        debug!("Fetching server config from {}/api/check", api_base);
+        let response = retry_with_backoff(|| async {
             client
-                .get("https://dash.bunkr.cr/api/check")
+                .get(format!("{}/api/check", api_base))
                 .header("token", &token)
                 .send().await
                 .map_err(anyhow::Error::from)
-        }, 5).await?;
+        }, 5, jitter).await?;
         let status = response.status();
         let text = response.text().await?;
         if !status.is_success() {
             return Err(anyhow!("Config fetch failed with status {}: {}", status, text));
         }
-        let config: BunkrConfig = match serde_json::from_str(&text) {
+        let server_config: BunkrConfig = match serde_json::from_str(&text) {
             Ok(r) => r,
             Err(e) => {
-                eprintln!("Failed to parse config response: {}", e);
-                eprintln!("Response: {}", text);
+                error!("Failed to parse config response: {}", e);
+                debug!("Response: {}", text);
                 return Err(anyhow!("JSON parsing error: {}", e));
             }
         };
 
-        // 95% of max size to account for overhead
-        let max_file_size = (parse_size(&config.maxSize)? as f64 * 0.95) as u64;
-        let chunk_size = parse_size(&config.chunkSize.default)?;
+        if server_config.maintenance && !config.ignore_maintenance.unwrap_or(false) {
+            return Err(anyhow!("Bunkr is in maintenance mode, uploads are likely to fail. Use --force to try anyway."));
+        }
+        if server_config.private && !verify.permissions.as_ref().is_some_and(|p| p.user) {
+            warn!("The server is private and this token doesn't appear to have upload permission");
+        }
+
+        let max_file_size = Self::resolve_max_file_size(&server_config.maxSize, config.max_size_margin, config.max_file_size.as_deref())?;
+        let chunk_size = Self::resolve_chunk_size(config.chunk_size.as_deref(), &server_config.chunkSize.default, &server_config.chunkSize.max)?;
 
         let mut headers = reqwest::header::HeaderMap::new();
-        headers.insert("token", token.parse()?);
-        let upload_url = Self::fetch_upload_url(&client, &headers).await?;
+        headers.insert("token", token.parse().map_err(|_| anyhow!("Malformed API token, it can't be sent as a header"))?);
+        let upload_url = Self::fetch_upload_url(&client, &api_base, &headers, jitter).await?;
 
         Ok(Self {
+            backend: Arc::new(BunkrBackend::new(client.clone())),
+            albums_cache: Arc::default(),
+            retry_jitter: jitter,
             client,
+            api_base,
             headers,
             upload_url,
             max_file_size,
             chunk_size,
+            send_file_hash: config.send_file_hash.unwrap_or(false),
+            rate_limiter: None,
+            bandwidth_limiter: None,
+            progress_tx: None,
+            account: Arc::new(verify),
+            strip_tags: config.strip_tags,
+            server_config: Arc::new(server_config),
+            used_names: None,
+            upload_name: None,
+            source_path: None,
+            display_names: Arc::new(HashMap::new()),
         })
     }
 
-    pub async fn upload_file(&self, path: &str, album_id: Option<&str>, ui_state: Option<Arc<Mutex<UIState>>>, config: &Config) -> Result<(Option<String>, Vec<FailedOperationInfo>)> {
+    /// An uploader that copies files into `dir` instead of uploading them, without contacting the
+    /// server. Useful to check what preprocessing and splitting produce; albums aren't available.
+    pub fn local(dir: impl AsRef<Path>, config: &Config) -> Result<Self> {
+        const DEFAULT_CHUNK_SIZE: &str = "25MB";
+
+        let max_file_size = match config.max_file_size.as_deref() {
+            Some(size) => parse_size(size)?,
+            None => u64::MAX,
+        };
+        Ok(Self {
+            client: build_client(config)?,
+            api_base: parse_base_url(config.base_url.as_deref().unwrap_or(DEFAULT_BASE_URL))?,
+            headers: reqwest::header::HeaderMap::new(),
+            upload_url: dir.as_ref().display().to_string(),
+            max_file_size,
+            chunk_size: Self::resolve_chunk_size(config.chunk_size.as_deref(), DEFAULT_CHUNK_SIZE, DEFAULT_CHUNK_SIZE)?,
+            send_file_hash: config.send_file_hash.unwrap_or(false),
+            rate_limiter: None,
+            bandwidth_limiter: None,
+            progress_tx: None,
+            account: Arc::new(VerifyResponse {
+                success: true,
+                username: None,
+                permissions: None,
+                group: None,
+                retentionPeriods: None,
+                defaultRetentionPeriod: None,
+            }),
+            strip_tags: config.strip_tags,
+            server_config: Arc::new(BunkrConfig::default()),
+            used_names: None,
+            upload_name: None,
+            source_path: None,
+            display_names: Arc::new(HashMap::new()),
+            backend: Arc::new(LocalFsBackend::new(dir)?),
+            albums_cache: Arc::default(),
+            retry_jitter: config.retry_jitter.unwrap_or(true),
+        })
+    }
+
+    /// Sends uploads through `backend` instead of the Bunkr server this uploader was created for.
+    pub fn with_backend(mut self, backend: Arc<dyn UploadBackend>) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Checks a requested upload age against the server's `temporaryUploadAges`.
+    pub fn validate_upload_age(&self, age: i64) -> Result<()> {
+        if self.server_config.temporaryUploadAges.contains(&age) {
+            Ok(())
+        } else {
+            let allowed: Vec<String> = self.server_config.temporaryUploadAges.iter().map(|a| a.to_string()).collect();
+            Err(anyhow!("Upload age {} is not allowed by the server, allowed ages: {}", age, if allowed.is_empty() { "none".to_string() } else { allowed.join(", ") }))
+        }
+    }
+
+    /// The `stripTags` value to send for a file, or `None` to leave it to the server.
+    /// The preference is dropped when the server forces its own setting, for blacklisted
+    /// extensions, and for videos when the server doesn't strip video tags.
+    fn strip_tags_for(&self, path: &Path, mime: &str) -> Option<bool> {
+        let strip_tags = self.strip_tags?;
+        if self.server_config.stripTags.force {
+            return None;
+        }
+        let extension = path.extension().map(|e| format!(".{}", e.to_string_lossy().to_lowercase()));
+        if let Some(extension) = extension {
+            if self.server_config.stripTags.blacklistExtensions.iter().any(|b| b.to_lowercase() == extension) {
+                return None;
+            }
+        }
+        if mime.starts_with("video/") && !self.server_config.stripTags.video {
+            return None;
+        }
+        Some(strip_tags)
+    }
+
+    /// The account details returned when the token was verified.
+    pub fn account(&self) -> &VerifyResponse {
+        &self.account
+    }
+
+    /// The server settings fetched from `/api/check`.
+    pub fn server_config(&self) -> &BunkrConfig {
+        &self.server_config
+    }
+
+    pub fn is_maintenance(&self) -> bool {
+        self.server_config.maintenance
+    }
+
+    pub fn is_private(&self) -> bool {
+        self.server_config.private
+    }
+
+    /// The upload node picked when the uploader was created.
+    pub fn upload_url(&self) -> &str {
+        &self.upload_url
+    }
+
+    /// Uploads `upload_files` batches under the given names instead of the files' own, keyed by path.
+    pub fn with_display_names(mut self, display_names: HashMap<String, String>) -> Self {
+        self.display_names = Arc::new(display_names);
+        self
+    }
+
+    pub async fn upload_file(&self, path: &str, album_id: Option<&str>, progress: Option<Arc<dyn ProgressSink + Send + Sync>>, config: &Config) -> Result<(Vec<UploadOutcome>, Vec<FailedOperationInfo>)> {
+        let display_name = self.display_names.get(path).cloned();
+        self.upload_file_named(path, display_name.as_deref(), album_id, progress, config).await
+    }
+
+    /// Uploads `path` so it shows up on Bunkr as `display_name`, e.g. `vacation.mp4` for `/tmp/abc123.mp4`.
+    /// Path separators in the name are replaced. Split parts are named after it as well.
+    pub async fn upload_file_as(&self, path: &str, display_name: &str, album_id: Option<&str>, progress: Option<Arc<dyn ProgressSink + Send + Sync>>, config: &Config) -> Result<(Vec<UploadOutcome>, Vec<FailedOperationInfo>)> {
+        self.upload_file_named(path, Some(display_name), album_id, progress, config).await
+    }
+
+    async fn upload_file_named(&self, path: &str, display_name: Option<&str>, album_id: Option<&str>, progress: Option<Arc<dyn ProgressSink + Send + Sync>>, config: &Config) -> Result<(Vec<UploadOutcome>, Vec<FailedOperationInfo>)> {
+        let display_name = display_name.map(sanitize_display_name).filter(|n| !n.is_empty());
         let p = Path::new(path);
         if !p.exists() {
             let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
-            #[cfg(feature = "ui")]
-            if let Some(ui_state) = &ui_state {
-                ui_state.lock().unwrap().add_failed_operation(path.to_string(), FailedOperationInfo {
-                    path: path.to_string(),
-                    error: format!("File not found: {}", path),
-                    file_size: size,
-                    status_code: None,
-                });
-            }
-            return Ok((None, vec![FailedOperationInfo {
+            let failure = FailedOperationInfo {
                 path: path.to_string(),
                 error: format!("File not found: {}", path),
                 file_size: size,
                 status_code: None,
-            }]));
+                source: None,
+            };
+            self.report_failure(&progress, &failure).await;
+            return Ok((vec![], vec![failure]));
         }
 
-        #[cfg(feature = "ui")]
-        {
-            let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
-            if let Some(ui_state) = &ui_state {
-                ui_state.lock().unwrap().add_preprocessing(path.to_string(), size);
-            }
+        // The server rejects empty files or returns an upload without a URL, so don't send them
+        if std::fs::metadata(path).map(|m| m.len()).unwrap_or(0) == 0 {
+            self.report_skip(&progress, path, "empty file").await;
+            return Ok((vec![], vec![]));
         }
 
-        let preprocess_result = crate::preprocess::preprocess::preprocess_file(path, self.max_file_size, config)?;
-        #[cfg(feature = "ui")]
-        if let Some(ui_state) = &ui_state {
-            if preprocess_result.files_to_upload.len() > 1 {
-                ui_state.lock().unwrap().add_to_total_files(preprocess_result.files_to_upload.len() - 1);
+        let age = progress.as_ref().and_then(|p| p.upload_age(path)).or(config.default_upload_age);
+        if let Some(age) = age {
+            if let Err(e) = self.validate_upload_age(age) {
+                let failure = FailedOperationInfo {
+                    path: path.to_string(),
+                    error: e.to_string(),
+                    file_size: std::fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+                    status_code: None,
+                    source: None,
+                };
+                self.report_failure(&progress, &failure).await;
+                return Ok((vec![], vec![failure]));
             }
         }
-        if preprocess_result.files_to_upload.len() == 1 && preprocess_result.files_to_upload[0] == path {
-            #[cfg(feature = "ui")]
-            if let Some(ui_state) = &ui_state {
-                let mut state = ui_state.lock().unwrap();
-                state.all_operations.insert(path.to_string(), OperationStatus::Ongoing(0.0));
+
+        if let Some(progress) = &progress {
+            let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+            progress.on_preprocess(path, size);
+        }
+
+        let on_preprocess_progress = |fraction: f64| {
+            if let Some(progress) = &progress {
+                progress.on_preprocess_progress(path, fraction);
             }
-        } else {
-            #[cfg(feature = "ui")]
-            if let Some(ui_state) = &ui_state {
-                let mut state = ui_state.lock().unwrap();
-                state.remove_operation(path);
+        };
+        let preprocess_result = match crate::preprocess::preprocess::preprocess_file_with_progress(path, self.max_file_size, config, &on_preprocess_progress) {
+            Ok(result) => result,
+            Err(e) => {
+                let failure = FailedOperationInfo {
+                    path: path.to_string(),
+                    error: format!("Preprocessing failed: {}", e),
+                    file_size: std::fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+                    status_code: None,
+                    source: None,
+                };
+                self.report_failure(&progress, &failure).await;
+                return Ok((vec![], vec![failure]));
             }
+        };
+        if let Some(progress) = &progress {
+            progress.on_preprocessed(path, &preprocess_result.files_to_upload);
         }
-        let mut urls = vec![];
+        let _cleanup = PreprocessGuard { result: &preprocess_result, original_path: path };
+        let rate_limiter = self.rate_limiter.clone()
+            .or_else(|| config.max_requests_per_second.map(|rps| Arc::new(RateLimiter::new(rps))));
+        let bandwidth_limiter = self.bandwidth_limiter.clone()
+            .or_else(|| config.max_upload_bytes_per_sec.map(|bps| Arc::new(BandwidthLimiter::new(bps))));
+        let mut outcomes = vec![];
         let mut file_fails = vec![];
         for file_path in &preprocess_result.files_to_upload {
             let p = Path::new(file_path);
@@ -201,66 +503,120 @@ impl BunkrUploader {
             }
             let metadata = p.metadata()?;
             let size = metadata.len();
-            let mime = from_path(p).first_or_octet_stream();
+            // Split parts are deleted once the file is done, so their failures point back at the original
+            let source = (file_path != path).then(|| path.to_string());
+
+            // Anything still over the limit after preprocessing would only be rejected by the server
+            if size > self.max_file_size {
+                let failure = FailedOperationInfo {
+                    path: file_path.clone(),
+                    error: format!("File size {} bytes exceeds max size {} bytes", size, self.max_file_size),
+                    file_size: size,
+                    status_code: None,
+                    source: source.clone(),
+                };
+                self.report_failure(&progress, &failure).await;
+                file_fails.push(failure);
+                continue;
+            }
+            let name = match &display_name {
+                Some(display_name) => Self::rename_part(p, Path::new(path), display_name),
+                None => disk_file_name(p),
+            };
+            let upload_name = self.reserve_name(&name).or(display_name.as_ref().map(|_| name));
             let mut upload_url = self.upload_url.clone();
             let mut last_result = (None, Vec::new());
+            let mut node_refreshes = 0;
 
             for attempt in 0..=Self::NODE_REFRESH_RETRIES {
                 let is_final_attempt = attempt == Self::NODE_REFRESH_RETRIES;
                 let uploader = BunkrUploader {
-                    client: self.client.clone(),
-                    headers: self.headers.clone(),
                     upload_url: upload_url.clone(),
-                    max_file_size: self.max_file_size,
-                    chunk_size: self.chunk_size,
+                    rate_limiter: rate_limiter.clone(),
+                    bandwidth_limiter: bandwidth_limiter.clone(),
+                    upload_name: upload_name.clone(),
+                    source_path: source.clone(),
+                    ..self.clone()
                 };
 
                 last_result = if size <= self.chunk_size {
                     uploader
-                        .upload_single_file(p, mime.essence_str(), album_id, ui_state.clone(), size, is_final_attempt)
+                        .upload_single_file(p, album_id, age, progress.clone(), size, is_final_attempt)
                         .await?
                 } else {
                     uploader
-                        .upload_chunked_file(p, mime.essence_str(), album_id, ui_state.clone(), size, is_final_attempt)
+                        .upload_chunked_file(p, album_id, age, progress.clone(), size, is_final_attempt)
                         .await?
                 };
 
-                if last_result.0.is_some() || last_result.1.is_empty() || is_final_attempt {
+                if last_result.0.is_some() || last_result.1.is_empty() || is_final_attempt || !self.backend.is_remote() {
+                    break;
+                }
+
+                // Client errors won't be fixed by switching nodes, fail fast
+                if last_result.1.iter().any(|f| Self::is_client_error(f.status_code)) {
+                    for failure in &last_result.1 {
+                        self.report_failure(&progress, failure).await;
+                    }
                     break;
                 }
 
-                upload_url = Self::fetch_upload_url(&self.client, &self.headers).await?;
+                upload_url = Self::fetch_upload_url(&self.client, &self.api_base, &self.headers, self.retry_jitter).await?;
+                node_refreshes += 1;
             }
 
-            let (url, fails) = last_result;
-            if let Some(u) = url {
-                urls.push(u);
+            let (outcome, fails) = last_result;
+            if let Some(mut o) = outcome {
+                o.retries += node_refreshes;
+                if let Some(mode) = config.verify_uploads.filter(|_| self.backend.is_remote()) {
+                    if let Err(e) = self.verify_upload(p, &o.url, size, mode).await {
+                        let failure = FailedOperationInfo {
+                            path: file_path.clone(),
+                            error: format!("Upload verification failed: {}", e),
+                            file_size: size,
+                            status_code: None,
+                            source: source.clone(),
+                        };
+                        self.report_failure(&progress, &failure).await;
+                        file_fails.push(failure);
+                        continue;
+                    }
+                }
+                outcomes.push(o);
             }
             file_fails.extend(fails);
         }
-        // Cleanup after upload
-        cleanup_preprocess(&preprocess_result.preprocess_id, path, &preprocess_result.files_to_upload);
-        Ok((Some(urls.join(",")), file_fails))
+        Ok((outcomes, file_fails))
     }
 
     async fn upload_single_file(
         &self,
         path: &Path,
-        mime: &str,
         album_id: Option<&str>,
-        _ui_state: Option<Arc<Mutex<UIState>>>,
+        age: Option<i64>,
+        progress: Option<Arc<dyn ProgressSink + Send + Sync>>,
         file_size: u64,
         record_failure: bool,
-    ) -> Result<(Option<String>, Vec<FailedOperationInfo>)> {
-        #[cfg(feature = "ui")]
-        let ui_state = _ui_state;
-        let file_name = path.file_name().unwrap().to_string_lossy().to_string();
+    ) -> Result<(Option<UploadOutcome>, Vec<FailedOperationInfo>)> {
+        let file_name = self.file_name_for(path);
+        let mime = from_path(path).first_or_octet_stream();
+        let strip_tags = self.strip_tags_for(path, mime.essence_str());
 
-        #[cfg(feature = "ui")]
-        if let Some(ui_state) = &ui_state {
-            let mut state = ui_state.lock().unwrap();
-            state.add_current_operation(path.to_string_lossy().to_string(), 0.0, file_size);
+        // Sending the hash means it must be known before the form is built. The file fits in one
+        // chunk, so it's read into memory once and hashed there rather than read again to send it.
+        // Otherwise it's hashed while streaming.
+        let contents = if self.send_file_hash {
+            Some(Bytes::from(tokio::fs::read(path).await?))
+        } else {
+            None
+        };
+        let precomputed_hash = contents.as_ref().map(|contents| format!("{:x}", Sha256::digest(contents)));
+        let hasher = Arc::new(Mutex::new(Sha256::new()));
+
+        if let Some(progress) = &progress {
+            progress.on_start(&path.to_string_lossy(), file_size);
         }
+        self.emit(ProgressEvent::Started { path: path.to_string_lossy().to_string(), size: file_size }).await;
 
         let headers = self.headers.clone();
         let headers = if let Some(album_id) = album_id {
@@ -271,20 +627,39 @@ impl BunkrUploader {
             headers
         };
 
-        let response = match Self::retry_with_backoff(|| async {
-            let file = TokioFile::open(path).await.map_err(anyhow::Error::from)?;
-            let stream = ReaderStream::new(file);
-            let body = Body::wrap_stream(stream);
-            let part = multipart::Part::stream(body).file_name(file_name.clone()).mime_str(mime).unwrap();
-            let form = multipart::Form::new().part("files[]", part);
-            self
-                .client
-                .post(&self.upload_url)
-                .headers(headers.clone())
-                .multipart(form)
-                .send().await
-                .map_err(anyhow::Error::from)
-        }, 5).await {
+        debug!("Uploading {} to {}", path.display(), self.upload_url);
+        let mut retries = 0;
+        let response = match retry_counted(|| async {
+            let data = match &contents {
+                Some(contents) => self.paced_body(stream::iter(Self::pace_pieces(contents))),
+                None => {
+                    let file = TokioFile::open(path).await.map_err(anyhow::Error::from)?;
+                    *hasher.lock().unwrap() = Sha256::new();
+                    let attempt_hasher = hasher.clone();
+                    let reader = InspectReader::new(file, move |bytes| attempt_hasher.lock().unwrap().update(bytes));
+                    self.paced_body(ReaderStream::new(reader))
+                }
+            };
+            let mut fields = vec![];
+            if let Some(hash) = &precomputed_hash {
+                fields.push(("hash", hash.clone()));
+            }
+            if let Some(age) = age {
+                fields.push(("age", age.to_string()));
+            }
+            if let Some(strip_tags) = strip_tags {
+                fields.push(("stripTags", strip_tags.to_string()));
+            }
+            let part = UploadPart {
+                file_name: file_name.clone(),
+                mime: mime.essence_str().to_string(),
+                length: contents.as_ref().map(|contents| contents.len() as u64),
+                fields,
+                data,
+            };
+            self.throttle().await;
+            self.backend.upload_part(&self.upload_url, &headers, part).await
+        }, 5, self.retry_jitter, &mut retries).await {
             Ok(response) => response,
             Err(e) => {
                 let failure = FailedOperationInfo {
@@ -292,156 +667,146 @@ impl BunkrUploader {
                     error: format!("Upload request failed: {}", e),
                     file_size,
                     status_code: None,
+                    source: None,
                 };
-                #[cfg(feature = "ui")]
-                if record_failure {
-                    if let Some(ui_state) = &ui_state {
-                        ui_state.lock().unwrap().add_failed_operation(path.to_string_lossy().to_string(), failure.clone());
-                    }
-                }
-                return Ok((None, vec![failure]));
+                return self.fail(&progress, failure, record_failure).await;
             }
         };
         let status = response.status();
         let text = response.text().await?;
         if !status.is_success() {
-            #[cfg(feature = "ui")]
-            if record_failure {
-                if let Some(ui_state) = &ui_state {
-                    ui_state.lock().unwrap().add_failed_operation(path.to_string_lossy().to_string(), FailedOperationInfo {
-                        path: path.to_string_lossy().to_string(),
-                        error: format!("Upload request failed with status {}: {}", status, text),
-                        file_size,
-                        status_code: Some(status.as_u16()),
-                    });
-                }
-            }
-            return Ok((None, vec![FailedOperationInfo {
+            return self.fail(&progress, FailedOperationInfo {
                 path: path.to_string_lossy().to_string(),
                 error: format!("Upload request failed with status {}: {}", status, text),
                 file_size,
                 status_code: Some(status.as_u16()),
-            }]));
+                source: None,
+            }, record_failure).await;
         }
         let res: UploadResponse = match serde_json::from_str(&text) {
             Ok(r) => r,
             Err(e) => {
-                #[cfg(feature = "ui")]
-                if record_failure {
-                    if let Some(ui_state) = &ui_state {
-                        ui_state.lock().unwrap().add_failed_operation(path.to_string_lossy().to_string(), FailedOperationInfo {
-                            path: path.to_string_lossy().to_string(),
-                            error: format!("Failed to parse upload response: {}", e),
-                            file_size,
-                            status_code: None,
-                        });
-                    }
-                }
-                return Ok((None, vec![FailedOperationInfo {
+                return self.fail(&progress, FailedOperationInfo {
                     path: path.to_string_lossy().to_string(),
                     error: format!("Failed to parse upload response: {}", e),
                     file_size,
                     status_code: None,
-                }]));
+                    source: None,
+                }, record_failure).await;
             }
         };
 
         if !res.success {
-            #[cfg(feature = "ui")]
-            if record_failure {
-                if let Some(ui_state) = &ui_state {
-                    ui_state.lock().unwrap().add_failed_operation(path.to_string_lossy().to_string(), FailedOperationInfo {
-                        path: path.to_string_lossy().to_string(),
-                        error: format!("Upload failed: server returned success=false"),
-                        file_size,
-                        status_code: None,
-                    });
-                }
-            }
-            return Ok((None, vec![FailedOperationInfo {
+            return self.fail(&progress, FailedOperationInfo {
                 path: path.to_string_lossy().to_string(),
-                error: format!("Upload failed: server returned success=false"),
+                error: "Upload failed: server returned success=false".to_string(),
                 file_size,
                 status_code: None,
-            }]));
+                source: None,
+            }, record_failure).await;
         }
 
-        let url = res.files.as_ref().and_then(|f| f.first().map(|x| x.url.clone()));
+        let Some(url) = Self::first_url(&res) else {
+            return self.fail(&progress, FailedOperationInfo {
+                path: path.to_string_lossy().to_string(),
+                error: "Upload failed: server returned success but no file URL".to_string(),
+                file_size,
+                status_code: None,
+                source: None,
+            }, record_failure).await;
+        };
+        let sha256 = precomputed_hash.unwrap_or_else(|| format!("{:x}", hasher.lock().unwrap().clone().finalize()));
 
-        {
-            #[cfg(feature = "ui")]
-            if let Some(ui_state) = &ui_state {
-                let mut state = ui_state.lock().unwrap();
-                state.update_progress(&path.to_string_lossy(), 1.0);
-                state.add_processed_bytes(file_size);
-                state.remove_current_operation(&path.to_string_lossy(), url.as_deref());
-            }
+        if let Some(progress) = &progress {
+            progress.on_progress(&path.to_string_lossy(), 1.0);
+            progress.on_bytes(&path.to_string_lossy(), file_size);
+            progress.on_complete(&path.to_string_lossy(), Some(&url));
         }
+        self.emit(ProgressEvent::Chunk { path: path.to_string_lossy().to_string(), uploaded: file_size, total: file_size }).await;
+        self.emit(ProgressEvent::Completed { path: path.to_string_lossy().to_string(), url: Some(url.clone()), retries }).await;
 
-        Ok((url, vec![]))
+        let outcome = UploadOutcome {
+            path: path.to_string_lossy().to_string(),
+            url,
+            file_size,
+            sha256: Some(sha256),
+            retries,
+        };
+        Ok((Some(outcome), vec![]))
     }
 
     async fn upload_chunked_file(
         &self,
         path: &Path,
-        mime: &str,
         album_id: Option<&str>,
-        _ui_state: Option<Arc<Mutex<UIState>>>,
+        age: Option<i64>,
+        progress: Option<Arc<dyn ProgressSink + Send + Sync>>,
         file_size: u64,
         record_failure: bool,
-    ) -> Result<(Option<String>, Vec<FailedOperationInfo>)> {
-        #[cfg(feature = "ui")]
-        let ui_state = _ui_state;
+    ) -> Result<(Option<UploadOutcome>, Vec<FailedOperationInfo>)> {
         let total_size = path.metadata()?.len();
         let total_chunks = (total_size as f64 / self.chunk_size as f64).ceil() as u64;
-        let file_name = path.file_name().unwrap().to_string_lossy().to_string();
+        let file_name = self.file_name_for(path);
+        let mime = from_path(path).first_or_octet_stream();
 
-        #[cfg(feature = "ui")]
-        if let Some(ui_state) = &ui_state {
-            let mut state = ui_state.lock().unwrap();
-            state.add_current_operation(path.to_string_lossy().to_string(), 0.0, total_size);
+        if let Some(progress) = &progress {
+            progress.on_start(&path.to_string_lossy(), total_size);
         }
+        self.emit(ProgressEvent::Started { path: path.to_string_lossy().to_string(), size: total_size }).await;
 
         let uuid = Uuid::new_v4();
         let mut file = TokioFile::open(path).await?;
-        let mut buf = Vec::with_capacity(self.chunk_size as usize);
+        // One fixed buffer for the whole file; each chunk is copied out once into a `Bytes`
+        // so retries share it instead of copying the chunk again.
+        let mut buf = vec![0u8; self.chunk_size as usize];
+        let mut hasher = Sha256::new();
+        let mut retries = 0;
+        // Chunks and bytes the server confirmed, checked against the file before finishing
+        let mut stored_chunks = 0u64;
+        let mut stored_bytes = 0u64;
 
         for i in 0..total_chunks {
-            buf.clear();
-            let mut total_read = 0;
-            let chunk_size_usize = self.chunk_size as usize;
-            while total_read < chunk_size_usize {
-                let remaining = chunk_size_usize - total_read;
-                buf.resize(total_read + remaining, 0);
-                let n = file.read(&mut buf[total_read..]).await?;
+            let mut bytes_read = 0;
+            while bytes_read < buf.len() {
+                let n = file.read(&mut buf[bytes_read..]).await?;
                 if n == 0 {
                     break;
                 }
-                total_read += n;
+                bytes_read += n;
             }
-            let bytes_read = total_read;
-            buf.truncate(bytes_read);
-
             let chunk_offset = i * self.chunk_size;
-            let response = match Self::retry_with_backoff(|| async {
-                let part = multipart::Part::bytes(buf.clone())
-                    .file_name(file_name.clone())
-                    .mime_str("application/octet-stream").unwrap();
-                let form = multipart::Form::new()
-                    .text("dzuuid", uuid.to_string())
-                    .text("dzchunkindex", i.to_string())
-                    .text("dztotalfilesize", total_size.to_string())
-                    .text("dzchunksize", self.chunk_size.to_string())
-                    .text("dztotalchunkcount", total_chunks.to_string())
-                    .text("dzchunkbyteoffset", chunk_offset.to_string())
-                    .part("files[]", part);
-                self.client
-                    .post(&self.upload_url)
-                    .headers(self.headers.clone())
-                    .multipart(form)
-                    .send().await
-                    .map_err(anyhow::Error::from)
-            }, 5).await {
+            let expected = (total_size - chunk_offset).min(self.chunk_size);
+            if bytes_read as u64 != expected {
+                return self.fail(&progress, FailedOperationInfo {
+                    path: path.to_string_lossy().to_string(),
+                    error: format!("File changed during upload: chunk {} has {} of {} bytes", i, bytes_read, expected),
+                    file_size,
+                    status_code: None,
+                    source: None,
+                }, record_failure).await;
+            }
+            let chunk = Bytes::copy_from_slice(&buf[..bytes_read]);
+            hasher.update(&chunk);
+
+            debug!("Uploading chunk {}/{} of {} to {}", i + 1, total_chunks, path.display(), self.upload_url);
+            let response = match retry_counted(|| async {
+                let part = UploadPart {
+                    file_name: file_name.clone(),
+                    mime: "application/octet-stream".to_string(),
+                    length: Some(chunk.len() as u64),
+                    fields: vec![
+                        ("dzuuid", uuid.to_string()),
+                        ("dzchunkindex", i.to_string()),
+                        ("dztotalfilesize", total_size.to_string()),
+                        ("dzchunksize", self.chunk_size.to_string()),
+                        ("dztotalchunkcount", total_chunks.to_string()),
+                        ("dzchunkbyteoffset", chunk_offset.to_string()),
+                    ],
+                    data: self.paced_body(stream::iter(Self::pace_pieces(&chunk))),
+                };
+                self.throttle().await;
+                self.backend.upload_part(&self.upload_url, &self.headers, part).await
+            }, 5, self.retry_jitter, &mut retries).await {
                 Ok(response) => response,
                 Err(e) => {
                     let failure = FailedOperationInfo {
@@ -449,49 +814,60 @@ impl BunkrUploader {
                         error: format!("Chunk {} upload failed: {}", i, e),
                         file_size,
                         status_code: None,
+                        source: None,
                     };
-                    #[cfg(feature = "ui")]
-                    if record_failure {
-                        if let Some(ui_state) = &ui_state {
-                            ui_state.lock().unwrap().add_failed_operation(path.to_string_lossy().to_string(), failure.clone());
-                        }
-                    }
-                    return Ok((None, vec![failure]));
+                    return self.fail(&progress, failure, record_failure).await;
                 }
             };
             let status = response.status();
+            let text = response.text().await?;
             if !status.is_success() {
-                let text = response.text().await?;
-                #[cfg(feature = "ui")]
-                if record_failure {
-                    if let Some(ui_state) = &ui_state {
-                        ui_state.lock().unwrap().add_failed_operation(path.to_string_lossy().to_string(), FailedOperationInfo {
-                            path: path.to_string_lossy().to_string(),
-                            error: format!("Chunk {} upload failed with status {}: {}", i, status, text),
-                            file_size,
-                            status_code: Some(status.as_u16()),
-                        });
-                    }
-                }
-                return Ok((None, vec![FailedOperationInfo {
+                return self.fail(&progress, FailedOperationInfo {
                     path: path.to_string_lossy().to_string(),
                     error: format!("Chunk {} upload failed with status {}: {}", i, status, text),
                     file_size,
                     status_code: Some(status.as_u16()),
-                }]));
+                    source: None,
+                }, record_failure).await;
             }
-
-            #[cfg(feature = "ui")]
-            {
-                let progress = (i + 1) as f64 / total_chunks as f64;
-                if let Some(ui_state) = &ui_state {
-                    let mut state = ui_state.lock().unwrap();
-                    state.update_progress(&path.to_string_lossy(), progress);
-                    state.add_processed_bytes(bytes_read as u64);
+            // Only a response saying `success: true` confirms the chunk, a truncated or empty body doesn't
+            match serde_json::from_str::<serde_json::Value>(&text).map(|res| res["success"].clone()) {
+                Ok(serde_json::Value::Bool(true)) => {
+                    stored_chunks += 1;
+                    stored_bytes += bytes_read as u64;
                 }
+                Ok(serde_json::Value::Bool(false)) => {
+                    return self.fail(&progress, FailedOperationInfo {
+                        path: path.to_string_lossy().to_string(),
+                        error: format!("Chunk {} was not stored: {}", i, text),
+                        file_size,
+                        status_code: Some(status.as_u16()),
+                        source: None,
+                    }, record_failure).await;
+                }
+                _ => warn!("Chunk {} of {} was not confirmed by the server: {}", i, path.display(), text),
+            }
+
+            if let Some(progress) = &progress {
+                progress.on_progress(&path.to_string_lossy(), (i + 1) as f64 / total_chunks as f64);
+                progress.on_bytes(&path.to_string_lossy(), bytes_read as u64);
             }
+            let uploaded = (chunk_offset + bytes_read as u64).min(total_size);
+            self.emit(ProgressEvent::Chunk { path: path.to_string_lossy().to_string(), uploaded, total: total_size }).await;
         }
         drop(buf);
+        let sha256 = format!("{:x}", hasher.finalize());
+
+        // Finishing with a chunk missing would only show up later as a broken file
+        if stored_chunks != total_chunks || stored_bytes != total_size {
+            return self.fail(&progress, FailedOperationInfo {
+                path: path.to_string_lossy().to_string(),
+                error: format!("Only {} of {} chunks ({} of {} bytes) were confirmed, not finishing the upload", stored_chunks, total_chunks, stored_bytes, total_size),
+                file_size,
+                status_code: None,
+                source: None,
+            }, record_failure).await;
+        }
 
         let url = {
             let finish_url = format!("{}/finishchunks", self.upload_url);
@@ -501,20 +877,19 @@ impl BunkrUploader {
                 "files": [{
                     "uuid": uuid.to_string(),
                     "original": original,
-                    "type": mime,
+                    "type": mime.essence_str(),
                     "albumid": albumid_value,
                     "filelength": null,
-                    "age": null,
+                    "age": age,
+                    "stripTags": self.strip_tags_for(path, mime.essence_str()),
+                    "hash": if self.send_file_hash { serde_json::Value::String(sha256.clone()) } else { serde_json::Value::Null },
                 }]
             });
-            let response = match Self::retry_with_backoff(|| async {
-                self.client
-                    .post(&finish_url)
-                    .headers(self.headers.clone())
-                    .json(&body)
-                    .send().await
-                    .map_err(anyhow::Error::from)
-            }, 5).await {
+            debug!("Finishing chunked upload of {} at {}", path.display(), finish_url);
+            let response = match retry_counted(|| async {
+                self.throttle().await;
+                self.backend.finish_chunks(&finish_url, &self.headers, &body).await
+            }, 5, self.retry_jitter, &mut retries).await {
                 Ok(response) => response,
                 Err(e) => {
                     let failure = FailedOperationInfo {
@@ -522,194 +897,487 @@ impl BunkrUploader {
                         error: format!("Finish chunks request failed: {}", e),
                         file_size,
                         status_code: None,
+                        source: None,
                     };
-                    #[cfg(feature = "ui")]
-                    if record_failure {
-                        if let Some(ui_state) = &ui_state {
-                            ui_state.lock().unwrap().add_failed_operation(path.to_string_lossy().to_string(), failure.clone());
-                        }
-                    }
-                    return Ok((None, vec![failure]));
+                    return self.fail(&progress, failure, record_failure).await;
                 }
             };
             let status = response.status();
             let text = response.text().await?;
             if !status.is_success() {
-                #[cfg(feature = "ui")]
-                if record_failure {
-                    if let Some(ui_state) = &ui_state {
-                        ui_state.lock().unwrap().add_failed_operation(path.to_string_lossy().to_string(), FailedOperationInfo {
-                            path: path.to_string_lossy().to_string(),
-                            error: format!("Finish chunks request failed with status {}: {}", status, text),
-                            file_size,
-                            status_code: Some(status.as_u16()),
-                        });
-                    }
-                }
-                return Ok((None, vec![FailedOperationInfo {
+                return self.fail(&progress, FailedOperationInfo {
                     path: path.to_string_lossy().to_string(),
                     error: format!("Finish chunks request failed with status {}: {}", status, text),
                     file_size,
                     status_code: Some(status.as_u16()),
-                }]));
+                    source: None,
+                }, record_failure).await;
             }
             let res: UploadResponse = match serde_json::from_str(&text) {
                 Ok(r) => r,
                 Err(e) => {
-                    #[cfg(feature = "ui")]
-                    if record_failure {
-                        if let Some(ui_state) = &ui_state {
-                            ui_state.lock().unwrap().add_failed_operation(path.to_string_lossy().to_string(), FailedOperationInfo {
-                                path: path.to_string_lossy().to_string(),
-                                error: format!("Failed to parse finish chunks response: {}", e),
-                                file_size,
-                                status_code: None,
-                            });
-                        }
-                    }
-                    return Ok((None, vec![FailedOperationInfo {
+                    return self.fail(&progress, FailedOperationInfo {
                         path: path.to_string_lossy().to_string(),
                         error: format!("Failed to parse finish chunks response: {}", e),
                         file_size,
                         status_code: None,
-                    }]));
+                        source: None,
+                    }, record_failure).await;
                 }
             };
             if !res.success {
-                #[cfg(feature = "ui")]
-                if record_failure {
-                    if let Some(ui_state) = &ui_state {
-                        ui_state.lock().unwrap().add_failed_operation(path.to_string_lossy().to_string(), FailedOperationInfo {
-                            path: path.to_string_lossy().to_string(),
-                            error: format!("Finish chunks failed: server returned success=false"),
-                            file_size,
-                            status_code: None,
-                        });
-                    }
-                }
-                return Ok((None, vec![FailedOperationInfo {
+                return self.fail(&progress, FailedOperationInfo {
                     path: path.to_string_lossy().to_string(),
-                    error: format!("Finish chunks failed: server returned success=false"),
+                    error: "Finish chunks failed: server returned success=false".to_string(),
                     file_size,
                     status_code: None,
-                }]));
+                    source: None,
+                }, record_failure).await;
+            }
+            match Self::first_url(&res) {
+                Some(url) => url,
+                None => {
+                    return self.fail(&progress, FailedOperationInfo {
+                        path: path.to_string_lossy().to_string(),
+                        error: "Finish chunks failed: server returned success but no file URL".to_string(),
+                        file_size,
+                        status_code: None,
+                        source: None,
+                    }, record_failure).await;
+                }
             }
-            res.files.and_then(|f| f.first().map(|x| x.url.clone()))
         };
 
-        {
-            #[cfg(feature = "ui")]
-            if let Some(ui_state) = &ui_state {
-                let mut state = ui_state.lock().unwrap();
-                state.remove_current_operation(&path.to_string_lossy(), url.as_deref());
+        if let Some(progress) = &progress {
+            progress.on_complete(&path.to_string_lossy(), Some(&url));
+        }
+        self.emit(ProgressEvent::Completed { path: path.to_string_lossy().to_string(), url: Some(url.clone()), retries }).await;
+
+        let outcome = UploadOutcome {
+            path: path.to_string_lossy().to_string(),
+            url,
+            file_size,
+            sha256: Some(sha256),
+            retries,
+        };
+        Ok((Some(outcome), vec![]))
+    }
+
+    /// URL of the first file in an upload response. A successful response without one would
+    /// otherwise leave the file out of both the results and the failures.
+    fn first_url(res: &UploadResponse) -> Option<String> {
+        res.files.as_ref()?.first().map(|f| f.url.clone()).filter(|url| !url.is_empty())
+    }
+
+    /// Checks the file stored at `url` against the local one, catching truncated uploads the
+    /// server still reported as successful.
+    async fn verify_upload(&self, path: &Path, url: &str, size: u64, mode: VerifyMode) -> Result<()> {
+        const PREFIX_LEN: u64 = 64 * 1024;
+
+        debug!("Verifying {} against {}", path.display(), url);
+        let response = retry_with_backoff(|| async {
+            self.client.head(url).send().await.map_err(anyhow::Error::from)
+        }, 3, self.retry_jitter).await?;
+        if !response.status().is_success() {
+            return Err(HttpStatusError {
+                status: response.status().as_u16(),
+                message: format!("HEAD {} returned status {}", url, response.status()),
+            }.into());
+        }
+        match response.content_length() {
+            Some(length) if length != size => {
+                return Err(anyhow!("server has {} bytes, expected {}", length, size));
+            }
+            Some(_) => {}
+            None => return Err(anyhow!("server didn't report a Content-Length")),
+        }
+
+        if mode == VerifyMode::Hash && size > 0 {
+            let len = size.min(PREFIX_LEN);
+            let response = retry_with_backoff(|| async {
+                self.client
+                    .get(url)
+                    .header(reqwest::header::RANGE, format!("bytes=0-{}", len - 1))
+                    .send().await
+                    .map_err(anyhow::Error::from)
+            }, 3, self.retry_jitter).await?;
+            // Anything but 206 means the range was ignored, don't pull the whole file to compare
+            if response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+                return Err(anyhow!("range request returned status {}", response.status()));
+            }
+            let remote = response.bytes().await?;
+            let mut local = vec![0u8; len as usize];
+            TokioFile::open(path).await?.read_exact(&mut local).await?;
+            if remote.as_ref() != local.as_slice() {
+                return Err(anyhow!("the first {} bytes on the server don't match the local file", len));
+            }
+        }
+        Ok(())
+    }
+
+    fn file_name_for(&self, path: &Path) -> String {
+        self.upload_name.clone().unwrap_or_else(|| disk_file_name(path))
+    }
+
+    /// Claims `name` in this batch. Returns a new name such as `IMG_001 (2).jpg` when
+    /// another file already took it, or `None` to keep it.
+    fn reserve_name(&self, name: &str) -> Option<String> {
+        let used_names = self.used_names.as_ref()?;
+        let mut used_names = used_names.lock().unwrap();
+        if used_names.insert(name.to_string()) {
+            return None;
+        }
+        let name = Path::new(name);
+        let stem = name.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        let extension = name.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+        (2..).map(|n| format!("{} ({}){}", stem, n, extension)).find(|candidate| used_names.insert(candidate.clone()))
+    }
+
+    /// Name for `part` of `original` uploaded as `display_name`. Split parts such as
+    /// `abc123_001.mp4` keep their suffix and become `vacation_001.mp4`.
+    fn rename_part(part: &Path, original: &Path, display_name: &str) -> String {
+        if part == original {
+            return display_name.to_string();
+        }
+        let part_name = disk_file_name(part);
+        let original_stem = original.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        let display_stem = Path::new(display_name).file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        match part_name.strip_prefix(&original_stem) {
+            Some(suffix) => format!("{}{}", display_stem, suffix),
+            None => part_name,
+        }
+    }
+
+    /// Reorders a batch by file size. Files whose size can't be read count as empty.
+    fn schedule_files(files: Vec<(String, Option<String>)>, schedule: UploadSchedule) -> Vec<(String, Option<String>)> {
+        if schedule == UploadSchedule::Input {
+            return files;
+        }
+        let mut sized: Vec<(u64, (String, Option<String>))> = files
+            .into_iter()
+            .map(|f| (std::fs::metadata(&f.0).map(|m| m.len()).unwrap_or(0), f))
+            .collect();
+        sized.sort_by_key(|(size, _)| *size);
+        match schedule {
+            UploadSchedule::SmallestFirst => sized.into_iter().map(|(_, f)| f).collect(),
+            UploadSchedule::LargestFirst => sized.into_iter().rev().map(|(_, f)| f).collect(),
+            _ => {
+                let mut sized = VecDeque::from(sized);
+                let mut ordered = Vec::with_capacity(sized.len());
+                while let Some((_, largest)) = sized.pop_back() {
+                    ordered.push(largest);
+                    if let Some((_, smallest)) = sized.pop_front() {
+                        ordered.push(smallest);
+                    }
+                }
+                ordered
             }
         }
+    }
 
-        Ok((url, vec![]))
+    /// Uploads `paths` and reports what happened to each file, uploads first, then failures.
+    /// This is the simplest way to upload from the library; `upload_files` exposes the event
+    /// channel and splits results and failures.
+    pub async fn upload(&self, paths: Vec<String>, opts: UploadOptions) -> Result<Vec<FileUploadOutcome>> {
+        let mut config = opts.config.unwrap_or_default();
+        if let Some(age) = opts.upload_age {
+            self.validate_upload_age(age)?;
+            config.default_upload_age = Some(age);
+        }
+        let album_id = opts.album_id.map(|id| id.to_string());
+        let (uploaded, failures) = self.upload_files(paths, album_id.as_deref(), opts.batch_size, opts.progress, Some(&config), None).await?;
+        Ok(uploaded.into_iter().map(FileUploadOutcome::Uploaded)
+            .chain(failures.into_iter().map(FileUploadOutcome::Failed))
+            .collect())
     }
 
+    /// Uploads every file in `files` into `album_id`, see `upload_files_to_albums`.
     pub async fn upload_files(
         &self,
         files: Vec<String>,
         album_id: Option<&str>,
         batch_size: usize,
-        ui_state: Option<Arc<Mutex<UIState>>>,
+        progress: Option<Arc<dyn ProgressSink + Send + Sync>>,
         config: Option<&Config>,
-    ) -> Result<(Vec<String>, Vec<FailedOperationInfo>)> {
+        events: Option<mpsc::Sender<ProgressEvent>>,
+    ) -> Result<(Vec<UploadOutcome>, Vec<FailedOperationInfo>)> {
+        let files = files.into_iter().map(|f| (f, album_id.map(str::to_string))).collect();
+        self.upload_files_to_albums(files, batch_size, progress, config, events).await
+    }
+
+    /// Uploads `(path, album id)` pairs, `batch_size` files at a time, so one batch can fill
+    /// several albums.
+    pub async fn upload_files_to_albums(
+        &self,
+        files: Vec<(String, Option<String>)>,
+        batch_size: usize,
+        progress: Option<Arc<dyn ProgressSink + Send + Sync>>,
+        config: Option<&Config>,
+        events: Option<mpsc::Sender<ProgressEvent>>,
+    ) -> Result<(Vec<UploadOutcome>, Vec<FailedOperationInfo>)> {
         let mut results = vec![];
         let mut failures = vec![];
         let batch_size = batch_size.max(1);
 
-        // Clone the necessary data to move into the async tasks
-        let client = self.client.clone();
-        let headers = self.headers.clone();
-        let upload_url = self.upload_url.clone();
-        let max_file_size = self.max_file_size;
-        let chunk_size = self.chunk_size;
-        let album_id_owned = album_id.map(|s| s.to_string());
-        let config_owned = config.cloned().unwrap_or_else(|| Config::default());
+        // Album of each file, for files the UI sends back to be retried
+        let albums: HashMap<String, Option<String>> = files.iter().cloned().collect();
+        let config_owned = config.cloned().unwrap_or_default();
+        // One limiter shared by every task so the cap applies across all files
+        let rate_limiter = self.rate_limiter.clone()
+            .or_else(|| config_owned.max_requests_per_second.map(|rps| Arc::new(RateLimiter::new(rps))));
+        let bandwidth_limiter = self.bandwidth_limiter.clone()
+            .or_else(|| config_owned.max_upload_bytes_per_sec.map(|bps| Arc::new(BandwidthLimiter::new(bps))));
+        let used_names = config_owned.rename_duplicates.unwrap_or(false).then(|| Arc::new(Mutex::new(HashSet::new())));
+        let base_uploader = BunkrUploader {
+            rate_limiter,
+            bandwidth_limiter,
+            used_names,
+            progress_tx: events.or_else(|| self.progress_tx.clone()),
+            ..self.clone()
+        };
 
+        let fail_fast = config_owned.fail_fast.unwrap_or(false);
+        let files = Self::schedule_files(files, config_owned.upload_schedule.unwrap_or_default());
         let mut files_iter = files.into_iter();
         let mut join_set = JoinSet::new();
+        // Path of each running task, so tasks cancelled by fail-fast can still be reported
+        let mut in_flight: HashMap<tokio::task::Id, String> = HashMap::new();
+        // Files the UI asked to retry, started ahead of the remaining files as slots free up
+        let mut retry_queue = VecDeque::new();
+        let mut retry_rx = progress.as_ref().and_then(|p| p.take_retry_receiver());
 
-        let mut spawn_next = |join_set: &mut JoinSet<Result<(Option<String>, Vec<FailedOperationInfo>)>>| {
-            let Some(file_path) = files_iter.next() else {
+        let mut spawn_next = |join_set: &mut JoinSet<(Vec<UploadOutcome>, Vec<FailedOperationInfo>)>, retry_queue: &mut VecDeque<String>, in_flight: &mut HashMap<tokio::task::Id, String>| {
+            let next = retry_queue.pop_front()
+                .map(|path| {
+                    let album_id = albums.get(&path).cloned().flatten();
+                    (path, album_id)
+                })
+                .or_else(|| files_iter.next());
+            let Some((file_path, album_id_owned)) = next else {
                 return;
             };
 
-            let client = client.clone();
-            let headers = headers.clone();
-            let upload_url = upload_url.clone();
-            let album_id_owned = album_id_owned.clone();
-            let ui_state = ui_state.clone();
+            let uploader = base_uploader.clone();
+            let progress = progress.clone();
             let config_owned = config_owned.clone();
 
-            join_set.spawn(async move {
-                let uploader = BunkrUploader {
-                    client,
-                    headers,
-                    upload_url,
-                    max_file_size,
-                    chunk_size,
-                };
-                uploader.upload_file(&file_path, album_id_owned.as_deref(), ui_state, &config_owned).await
+            let task_path = file_path.clone();
+            let handle = join_set.spawn(async move {
+                // Any error here would otherwise leave the file out of both results and failures
+                match uploader.upload_file(&file_path, album_id_owned.as_deref(), progress.clone(), &config_owned).await {
+                    Ok(result) => result,
+                    Err(e) => (vec![], vec![uploader.report_task_failure(&progress, file_path, e.to_string()).await]),
+                }
             });
+            in_flight.insert(handle.id(), task_path);
         };
 
         for _ in 0..batch_size {
-            spawn_next(&mut join_set);
+            spawn_next(&mut join_set, &mut retry_queue, &mut in_flight);
         }
 
-        while let Some(result) = join_set.join_next().await {
-            if let Ok(Ok((url, fails))) = result {
-                if let Some(u) = url {
-                    results.push(u);
+        while !join_set.is_empty() {
+            tokio::select! {
+                Some(result) = join_set.join_next_with_id() => {
+                    let mut failed = false;
+                    match result {
+                        Ok((id, (outcomes, fails))) => {
+                            in_flight.remove(&id);
+                            failed = !fails.is_empty();
+                            results.extend(outcomes);
+                            failures.extend(fails);
+                        }
+                        Err(e) => {
+                            if let Some(path) = in_flight.remove(&e.id()) {
+                                failures.push(base_uploader.report_task_failure(&progress, path, format!("Upload task failed: {}", e)).await);
+                                failed = true;
+                            }
+                        }
+                    }
+
+                    if fail_fast && failed {
+                        // Aborted tasks drop their preprocess guard, which removes any split files
+                        join_set.abort_all();
+                        while let Some(result) = join_set.join_next_with_id().await {
+                            match result {
+                                Ok((id, (outcomes, fails))) => {
+                                    in_flight.remove(&id);
+                                    results.extend(outcomes);
+                                    failures.extend(fails);
+                                }
+                                Err(e) => {
+                                    if let Some(path) = in_flight.remove(&e.id()) {
+                                        let error = if e.is_cancelled() {
+                                            "Cancelled after an earlier failure (fail-fast)".to_string()
+                                        } else {
+                                            format!("Upload task failed: {}", e)
+                                        };
+                                        failures.push(base_uploader.report_task_failure(&progress, path, error).await);
+                                    }
+                                }
+                            }
+                        }
+                        break;
+                    }
+
+                    spawn_next(&mut join_set, &mut retry_queue, &mut in_flight);
+
+                    // Retries asked for as the last file finished would otherwise end with the batch.
+                    // Once nothing is left, the receiver is closed so later requests are refused, not lost.
+                    if join_set.is_empty() {
+                        if let Some(rx) = retry_rx.as_mut() {
+                            let mut pending: Vec<String> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+                            if pending.is_empty() {
+                                rx.close();
+                                pending.extend(std::iter::from_fn(|| rx.try_recv().ok()));
+                            }
+                            for path in pending {
+                                let path = failures.iter().find(|f| f.path == path).and_then(|f| f.source.clone()).unwrap_or(path);
+                                failures.retain(|f: &FailedOperationInfo| f.path != path && f.source.as_ref() != Some(&path));
+                                retry_queue.push_back(path);
+                            }
+                            for _ in 0..batch_size {
+                                spawn_next(&mut join_set, &mut retry_queue, &mut in_flight);
+                            }
+                        }
+                    }
+                }
+                Some(path) = async { retry_rx.as_mut()?.recv().await }, if retry_rx.is_some() => {
+                    // A split part is gone by now, so its original is uploaded (and split) again
+                    let path = failures.iter().find(|f| f.path == path).and_then(|f| f.source.clone()).unwrap_or(path);
+                    failures.retain(|f: &FailedOperationInfo| f.path != path && f.source.as_ref() != Some(&path));
+                    retry_queue.push_back(path);
+                    if join_set.len() < batch_size {
+                        spawn_next(&mut join_set, &mut retry_queue, &mut in_flight);
+                    }
                 }
-                failures.extend(fails);
             }
-
-            spawn_next(&mut join_set);
         }
 
         Ok((results, failures))
     }
 
+    /// Lists the account's albums, fetching every page.
     pub async fn get_albums(&self) -> Result<Vec<Album>> {
         #[derive(serde::Deserialize)]
         struct AlbumsResponse {
+            #[serde(default)]
             albums: Vec<Album>,
+            count: Option<usize>,
         }
-        let response = Self::retry_with_backoff(|| async {
-            self.client
-                .get("https://dash.bunkr.cr/api/albums")
-                .headers(self.headers.clone())
-                .send().await
-                .map_err(anyhow::Error::from)
-        }, 5).await?;
-        let status = response.status();
-        let text = response.text().await?;
-        if !status.is_success() {
-            return Err(anyhow!("Albums fetch failed with status {}: {}", status, text));
+        let mut albums: Vec<Album> = vec![];
+        let mut seen = HashSet::new();
+        for page in 0.. {
+            debug!("Fetching page {} of albums", page);
+            let response = retry_with_backoff(|| async {
+                self.client
+                    .get(format!("{}/api/albums/{}", self.api_base, page))
+                    .headers(self.headers.clone())
+                    .send().await
+                    .map_err(anyhow::Error::from)
+            }, 5, self.retry_jitter).await?;
+            let status = response.status();
+            let text = response.text().await?;
+            if !status.is_success() {
+                return Err(anyhow!("Albums fetch failed with status {}: {}", status, text));
+            }
+            let res: AlbumsResponse = match serde_json::from_str(&text) {
+                Ok(r) => r,
+                Err(e) => {
+                    error!("Failed to parse albums response: {}", e);
+                    debug!("Response: {}", text);
+                    return Err(anyhow!("JSON parsing error: {}", e));
+                }
+            };
+            // A server that ignores the page number sends the same albums again, stop there
+            let new: Vec<Album> = res.albums.into_iter().filter(|a| seen.insert(a.id)).collect();
+            if new.is_empty() {
+                break;
+            }
+            albums.extend(new);
+            if res.count.is_some_and(|count| albums.len() >= count) {
+                break;
+            }
         }
-        let res: AlbumsResponse = match serde_json::from_str(&text) {
-            Ok(r) => r,
-            Err(e) => {
-                eprintln!("Failed to parse albums response: {}", e);
-                eprintln!("Response: {}", text);
-                return Err(anyhow!("JSON parsing error: {}", e));
+        Ok(albums)
+    }
+
+    /// Lists the files already in one of the account's albums, fetching every page.
+    pub async fn get_album_files(&self, album_id: i64) -> Result<Vec<AlbumFile>> {
+        #[derive(serde::Deserialize)]
+        struct AlbumFilesResponse {
+            success: bool,
+            #[serde(default)]
+            files: Vec<AlbumFile>,
+            count: Option<usize>,
+        }
+        let mut files: Vec<AlbumFile> = vec![];
+        let mut seen = HashSet::new();
+        for page in 0.. {
+            debug!("Fetching page {} of album {} files", page, album_id);
+            let response = retry_with_backoff(|| async {
+                self.client
+                    .get(format!("{}/api/album/{}/{}", self.api_base, album_id, page))
+                    .headers(self.headers.clone())
+                    .send().await
+                    .map_err(anyhow::Error::from)
+            }, 5, self.retry_jitter).await?;
+            let status = response.status();
+            let text = response.text().await?;
+            if !status.is_success() {
+                return Err(anyhow!("Album files fetch failed with status {}: {}", status, text));
             }
-        };
-        Ok(res.albums)
+            let res: AlbumFilesResponse = match serde_json::from_str(&text) {
+                Ok(r) => r,
+                Err(e) => {
+                    error!("Failed to parse album files response: {}", e);
+                    debug!("Response: {}", text);
+                    return Err(anyhow!("JSON parsing error: {}", e));
+                }
+            };
+            if !res.success {
+                return Err(anyhow!("Album files fetch failed: server returned success=false"));
+            }
+            // Same as in `get_albums`, a page with nothing new means the server ignored the page number
+            let new: Vec<AlbumFile> = res.files.into_iter().filter(|f| seen.insert(f.id)).collect();
+            if new.is_empty() {
+                break;
+            }
+            files.extend(new);
+            if res.count.is_some_and(|count| files.len() >= count) {
+                break;
+            }
+        }
+        Ok(files)
     }
 
+    /// Looks up an album id by name (case-insensitive). The album list is fetched once and reused
+    /// for later lookups; call `refresh_albums` to pick up changes made elsewhere.
     pub async fn get_album_by_name(&self, album_name: &str) -> Result<Option<i64>> {
-        let albums = self.get_albums().await?;
-        for album in albums {
-            if album.name.to_lowercase() == album_name.to_lowercase() {
-                return Ok(Some(album.id));
-            }
+        let album_name = album_name.to_lowercase();
+        Ok(self.find_album(|album| album.name.to_lowercase() == album_name).await?.map(|album| album.id))
+    }
+
+    /// Looks up an album by id in the same cached album list as `get_album_by_name`.
+    pub async fn get_album(&self, album_id: i64) -> Result<Option<Album>> {
+        self.find_album(|album| album.id == album_id).await
+    }
+
+    async fn find_album(&self, matches: impl Fn(&Album) -> bool) -> Result<Option<Album>> {
+        // Held across the fetch so concurrent lookups wait for one request instead of each sending their own
+        let mut cache = self.albums_cache.lock().await;
+        if cache.is_none() {
+            *cache = Some(self.get_albums().await?);
         }
-        Ok(None)
+        let albums = cache.as_deref().unwrap_or_default();
+        Ok(albums.iter().find(|album| matches(album)).cloned())
+    }
+
+    /// Fetches the album list again, replacing the one cached for `get_album_by_name`.
+    pub async fn refresh_albums(&self) -> Result<Vec<Album>> {
+        let albums = self.get_albums().await?;
+        *self.albums_cache.lock().await = Some(albums.clone());
+        Ok(albums)
     }
 
     pub async fn create_album(&self, name: String, description: Option<String>, download: bool, public: bool) -> Result<i64> {
@@ -720,14 +1388,14 @@ impl BunkrUploader {
             "public": public,
         });
 
-        let response = Self::retry_with_backoff(|| async {
+        let response = retry_with_backoff(|| async {
             self.client
-                .post("https://dash.bunkr.cr/api/albums")
+                .post(format!("{}/api/albums", self.api_base))
                 .headers(self.headers.clone())
                 .json(&body)
                 .send().await
                 .map_err(anyhow::Error::from)
-        }, 5).await?;
+        }, 5, self.retry_jitter).await?;
 
         let status = response.status();
         let text = response.text().await?;
@@ -737,9 +1405,367 @@ impl BunkrUploader {
 
         let res: serde_json::Value = serde_json::from_str(&text)?;
         if res["success"] == true {
-            Ok(res["id"].as_i64().unwrap())
+            let id = res["id"].as_i64().ok_or_else(|| anyhow!("Create album response did not include an album id: {}", text))?;
+            // Keep the cached list current so the next lookup doesn't have to fetch it again
+            if let Some(albums) = self.albums_cache.lock().await.as_mut() {
+                albums.push(Album { id, name });
+            }
+            Ok(id)
         } else {
             Err(anyhow!("Create album failed: success=false"))
         }
     }
+
+    /// Updates an existing album. Only the fields that are `Some` are sent, so omitted settings keep their server-side values.
+    pub async fn update_album(&self, album_id: i64, description: Option<String>, download: Option<bool>, public: Option<bool>) -> Result<()> {
+        let mut body = serde_json::Map::new();
+        body.insert("id".to_string(), json!(album_id));
+        if let Some(description) = description {
+            body.insert("description".to_string(), json!(description));
+        }
+        if let Some(download) = download {
+            body.insert("download".to_string(), json!(download));
+        }
+        if let Some(public) = public {
+            body.insert("public".to_string(), json!(public));
+        }
+        let body = serde_json::Value::Object(body);
+
+        let response = retry_with_backoff(|| async {
+            self.client
+                .post(format!("{}/api/albums/edit", self.api_base))
+                .headers(self.headers.clone())
+                .json(&body)
+                .send().await
+                .map_err(anyhow::Error::from)
+        }, 5, self.retry_jitter).await?;
+
+        let status = response.status();
+        let text = response.text().await?;
+        if !status.is_success() {
+            return Err(anyhow!("Edit album failed with status {}: {}", status, text));
+        }
+
+        let res: serde_json::Value = serde_json::from_str(&text)?;
+        if res["success"] == true {
+            Ok(())
+        } else {
+            let description = res["description"].as_str().unwrap_or("success=false");
+            Err(anyhow!("Edit album failed: {}", description))
+        }
+    }
+
+    /// Changes the name a file is shown under. Separators and other characters that aren't
+    /// allowed in file names are replaced, see `sanitize_filename`.
+    pub async fn rename_file(&self, file_id: i64, new_name: String) -> Result<()> {
+        let name = sanitize_filename(new_name.trim());
+        if name.is_empty() {
+            return Err(anyhow!("New file name is empty"));
+        }
+        let body = json!({
+            "id": file_id,
+            "name": name,
+        });
+
+        let response = retry_with_backoff(|| async {
+            self.client
+                .post(format!("{}/api/upload/rename", self.api_base))
+                .headers(self.headers.clone())
+                .json(&body)
+                .send().await
+                .map_err(anyhow::Error::from)
+        }, 5, self.retry_jitter).await?;
+
+        let status = response.status();
+        let text = response.text().await?;
+        if !status.is_success() {
+            return Err(anyhow!("Rename file failed with status {}: {}", status, text));
+        }
+
+        let res: serde_json::Value = serde_json::from_str(&text)?;
+        if res["success"] == true {
+            Ok(())
+        } else {
+            let description = res["description"].as_str().unwrap_or("success=false");
+            Err(anyhow!("Rename file failed: {}", description))
+        }
+    }
+
+    /// Moves files into `album_id`. A file is in at most one album, so this also takes it out of
+    /// the album it was in.
+    pub async fn move_files(&self, file_ids: &[i64], album_id: i64) -> Result<()> {
+        self.set_files_album(file_ids, Some(album_id)).await
+    }
+
+    /// Moves a single file into `album_id`, see `move_files`.
+    pub async fn move_file(&self, file_id: i64, album_id: i64) -> Result<()> {
+        self.move_files(&[file_id], album_id).await
+    }
+
+    /// Takes files out of whatever album they're in, without deleting them.
+    pub async fn remove_from_album(&self, file_ids: &[i64]) -> Result<()> {
+        self.set_files_album(file_ids, None).await
+    }
+
+    async fn set_files_album(&self, file_ids: &[i64], album_id: Option<i64>) -> Result<()> {
+        let body = json!({
+            "ids": file_ids,
+            "albumid": album_id,
+        });
+
+        let response = retry_with_backoff(|| async {
+            self.client
+                .post(format!("{}/api/albums/addfiles", self.api_base))
+                .headers(self.headers.clone())
+                .json(&body)
+                .send().await
+                .map_err(anyhow::Error::from)
+        }, 5, self.retry_jitter).await?;
+
+        let status = response.status();
+        let text = response.text().await?;
+        if !status.is_success() {
+            return Err(anyhow!("Moving files failed with status {}: {}", status, text));
+        }
+
+        let res: serde_json::Value = serde_json::from_str(&text)?;
+        if res["success"] != true {
+            let description = res["description"].as_str().unwrap_or("success=false");
+            return Err(anyhow!("Moving files failed: {}", description));
+        }
+        // The request succeeds as long as one file could be moved, the rest are listed here
+        let failed: Vec<String> = res["failed"].as_array().map(|ids| ids.iter().map(|id| id.to_string()).collect()).unwrap_or_default();
+        if !failed.is_empty() {
+            return Err(anyhow!("Could not move file{} {}", if failed.len() == 1 { "" } else { "s" }, failed.join(", ")));
+        }
+        Ok(())
+    }
+}
+
+/// Name to upload a file under. Invalid UTF-8 would otherwise become U+FFFD and no longer match
+/// the file, so those bytes are replaced with `_` instead. Paths without a final component, such
+/// as ones ending in `..`, fall back to "file" instead of panicking.
+fn disk_file_name(path: &Path) -> String {
+    let Some(name) = path.file_name() else {
+        return "file".to_string();
+    };
+    match name.to_str() {
+        Some(name) => name.to_string(),
+        None => {
+            let renamed = name.to_string_lossy().replace(char::REPLACEMENT_CHARACTER, "_");
+            warn!("{} isn't valid UTF-8, uploading it as {}", path.display(), renamed);
+            renamed
+        }
+    }
+}
+
+/// Replaces path separators so a display name can't point outside the file's own name.
+fn sanitize_display_name(name: &str) -> String {
+    name.trim().replace(['/', '\\'], "_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Records the files reported as skipped.
+    #[derive(Default)]
+    struct SkipRecorder {
+        skipped: Mutex<Vec<(String, String)>>,
+    }
+
+    impl ProgressSink for SkipRecorder {
+        fn on_start(&self, _path: &str, _size: u64) {}
+        fn on_progress(&self, _path: &str, _progress: f64) {}
+        fn on_complete(&self, _path: &str, _url: Option<&str>) {}
+        fn on_fail(&self, _path: &str, _info: &FailedOperationInfo) {}
+
+        fn on_skip(&self, path: &str, reason: &str) {
+            self.skipped.lock().unwrap().push((path.to_string(), reason.to_string()));
+        }
+    }
+
+    /// Asks to retry each file once, as soon as it completes.
+    struct RetryOnce {
+        retry_tx: tokio::sync::mpsc::UnboundedSender<String>,
+        retry_rx: Mutex<Option<tokio::sync::mpsc::UnboundedReceiver<String>>>,
+        retried: Mutex<HashSet<String>>,
+    }
+
+    impl ProgressSink for RetryOnce {
+        fn on_start(&self, _path: &str, _size: u64) {}
+        fn on_progress(&self, _path: &str, _progress: f64) {}
+        fn on_fail(&self, _path: &str, _info: &FailedOperationInfo) {}
+
+        fn on_complete(&self, path: &str, _url: Option<&str>) {
+            if self.retried.lock().unwrap().insert(path.to_string()) {
+                self.retry_tx.send(path.to_string()).unwrap();
+            }
+        }
+
+        fn take_retry_receiver(&self) -> Option<tokio::sync::mpsc::UnboundedReceiver<String>> {
+            self.retry_rx.lock().unwrap().take()
+        }
+    }
+
+    #[tokio::test]
+    async fn retry_asked_for_as_the_last_file_finishes_is_uploaded() {
+        let source = tempfile::tempdir().unwrap();
+        let target = tempfile::tempdir().unwrap();
+        let path = source.path().join("last.txt");
+        std::fs::write(&path, b"last one").unwrap();
+        let path = path.to_string_lossy().to_string();
+
+        let (retry_tx, retry_rx) = tokio::sync::mpsc::unbounded_channel();
+        let sink = Arc::new(RetryOnce { retry_tx, retry_rx: Mutex::new(Some(retry_rx)), retried: Mutex::default() });
+        let config = Config::default();
+        let uploader = BunkrUploader::local(target.path(), &config).unwrap();
+        let (outcomes, failures) = uploader.upload_files(vec![path.clone()], None, 1, Some(sink), Some(&config), None).await.unwrap();
+
+        assert!(failures.is_empty(), "{:?}", failures);
+        assert_eq!(outcomes.len(), 2);
+        assert!(outcomes.iter().all(|o| o.path == path));
+    }
+
+    #[tokio::test]
+    async fn empty_file_is_skipped() {
+        let source = tempfile::tempdir().unwrap();
+        let target = tempfile::tempdir().unwrap();
+        let path = source.path().join("empty.txt");
+        std::fs::write(&path, b"").unwrap();
+        let path = path.to_string_lossy().to_string();
+
+        let config = Config::default();
+        let uploader = BunkrUploader::local(target.path(), &config).unwrap();
+        let recorder = Arc::new(SkipRecorder::default());
+        let (outcomes, failures) = uploader.upload_file(&path, None, Some(recorder.clone()), &config).await.unwrap();
+
+        assert!(outcomes.is_empty());
+        assert!(failures.is_empty());
+        assert_eq!(*recorder.skipped.lock().unwrap(), vec![(path, "empty file".to_string())]);
+        assert_eq!(std::fs::read_dir(target.path()).unwrap().count(), 0);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn failed_preprocessing_only_fails_its_own_file() {
+        let source = tempfile::tempdir().unwrap();
+        let target = tempfile::tempdir().unwrap();
+        let files: Vec<String> = ["good.txt", "bad.txt", "fine.txt"].iter().map(|name| {
+            let path = source.path().join(name);
+            std::fs::write(&path, name.as_bytes()).unwrap();
+            path.to_string_lossy().to_string()
+        }).collect();
+
+        let config = Config {
+            preprocess_command: Some("case {input} in *bad.txt) echo refused >&2; exit 3;; *) cp {input} {outdir}/;; esac".to_string()),
+            ..Config::default()
+        };
+        let uploader = BunkrUploader::local(target.path(), &config).unwrap();
+        let (outcomes, failures) = uploader.upload_files(files.clone(), None, 2, None, Some(&config), None).await.unwrap();
+
+        assert_eq!(outcomes.len(), 2, "{:?}", failures);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].path, files[1]);
+        assert!(failures[0].error.starts_with("Preprocessing failed"), "{}", failures[0].error);
+        assert!(target.path().join("good.txt").exists());
+        assert!(target.path().join("fine.txt").exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn failed_part_points_back_at_its_original() {
+        let source = tempfile::tempdir().unwrap();
+        let target = tempfile::tempdir().unwrap();
+        let path = source.path().join("long.bin");
+        std::fs::write(&path, [1u8; 100]).unwrap();
+        let path = path.to_string_lossy().to_string();
+
+        let config = Config {
+            max_file_size: Some("50B".to_string()),
+            preprocess_command: Some("head -c 40 {input} > {outdir}/part1.bin; cp {input} {outdir}/part2.bin".to_string()),
+            ..Config::default()
+        };
+        let uploader = BunkrUploader::local(target.path(), &config).unwrap();
+        let (outcomes, failures) = uploader.upload_files(vec![path.clone()], None, 1, None, Some(&config), None).await.unwrap();
+
+        assert_eq!(outcomes.len(), 1);
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].path.ends_with("part2.bin"), "{}", failures[0].path);
+        assert!(!Path::new(&failures[0].path).exists());
+        assert_eq!(failures[0].source.as_deref(), Some(path.as_str()));
+    }
+
+    #[test]
+    fn disk_file_name_falls_back_for_paths_without_a_name() {
+        assert_eq!(disk_file_name(Path::new("dir/photo.jpg")), "photo.jpg");
+        assert_eq!(disk_file_name(Path::new("dir/..")), "file");
+        assert_eq!(disk_file_name(Path::new("/")), "file");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn disk_file_name_replaces_invalid_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+        let name = std::ffi::OsStr::from_bytes(b"caf\xe9 menu.jpg");
+        assert_eq!(disk_file_name(&Path::new("dir").join(name)), "caf_ menu.jpg");
+    }
+
+    #[test]
+    fn max_file_size_leaves_the_default_margin() {
+        assert_eq!(BunkrUploader::resolve_max_file_size("2GB", None, None).unwrap(), 1_900_000_000);
+    }
+
+    #[test]
+    fn max_file_size_uses_the_configured_margin() {
+        assert_eq!(BunkrUploader::resolve_max_file_size("2GB", Some(0.5), None).unwrap(), 1_000_000_000);
+        assert_eq!(BunkrUploader::resolve_max_file_size("2GB", Some(1.0), None).unwrap(), 2_000_000_000);
+    }
+
+    #[test]
+    fn max_file_size_margin_must_be_a_fraction() {
+        for margin in [0.0, -0.5, 1.01, f64::NAN] {
+            assert!(BunkrUploader::resolve_max_file_size("2GB", Some(margin), None).is_err(), "{} was accepted", margin);
+        }
+    }
+
+    #[test]
+    fn configured_max_file_size_never_exceeds_the_server_limit() {
+        assert_eq!(BunkrUploader::resolve_max_file_size("2GB", None, Some("500MB")).unwrap(), 500_000_000);
+        assert_eq!(BunkrUploader::resolve_max_file_size("2GB", None, Some("5GB")).unwrap(), 1_900_000_000);
+    }
+
+    #[test]
+    fn chunk_size_defaults_to_the_server_value() {
+        assert_eq!(BunkrUploader::resolve_chunk_size(None, "25MB", "95MB").unwrap(), 25_000_000);
+    }
+
+    #[test]
+    fn chunk_size_below_the_server_max_is_honored() {
+        assert_eq!(BunkrUploader::resolve_chunk_size(Some("50MB"), "25MB", "95MB").unwrap(), 50_000_000);
+    }
+
+    #[test]
+    fn chunk_size_above_the_server_max_is_clamped() {
+        assert_eq!(BunkrUploader::resolve_chunk_size(Some("200MB"), "25MB", "95MB").unwrap(), 95_000_000);
+    }
+
+    #[test]
+    fn zero_or_unparsable_chunk_size_falls_back_to_the_server_default() {
+        assert_eq!(BunkrUploader::resolve_chunk_size(Some("0MB"), "25MB", "95MB").unwrap(), 25_000_000);
+        assert_eq!(BunkrUploader::resolve_chunk_size(Some("lots"), "25MB", "95MB").unwrap(), 25_000_000);
+    }
+
+    #[test]
+    fn pathological_chunk_size_is_capped() {
+        // A server whose default and max are both huge still can't make a chunk larger than the cap
+        assert_eq!(BunkrUploader::resolve_chunk_size(None, "5GB", "10GB").unwrap(), BunkrUploader::MAX_CHUNK_SIZE);
+        assert_eq!(BunkrUploader::resolve_chunk_size(Some("8GB"), "25MB", "10GB").unwrap(), BunkrUploader::MAX_CHUNK_SIZE);
+        assert_eq!(BunkrUploader::resolve_chunk_size(Some("100MB"), "25MB", "10GB").unwrap(), 100_000_000);
+    }
+
+    #[test]
+    fn unparsable_server_chunk_size_is_an_error() {
+        assert!(BunkrUploader::resolve_chunk_size(None, "big", "95MB").is_err());
+    }
 }
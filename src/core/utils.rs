@@ -1,4 +1,9 @@
+use crate::config::config::{Config, DEFAULT_CONNECT_TIMEOUT_SECS, DEFAULT_REQUEST_TIMEOUT_SECS, DEFAULT_USER_AGENT};
+use crate::core::types::{AlbumFile, BunkrUrl};
 use anyhow::Result;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::warn;
 #[cfg(feature = "cli")]
 use keyring::Entry;
 
@@ -17,17 +22,372 @@ pub fn parse_size(size_str: &str) -> Result<u64> {
     }
 }
 
+/// Builds the HTTP client shared by the uploader and downloader. Timeouts apply per request,
+/// so a chunked upload is bounded per chunk and a timed-out request goes through the normal retry path.
+pub fn build_client(config: &Config) -> Result<reqwest::Client> {
+    let request_timeout = config.request_timeout_secs.unwrap_or(DEFAULT_REQUEST_TIMEOUT_SECS);
+    let connect_timeout = config.connect_timeout_secs.unwrap_or(DEFAULT_CONNECT_TIMEOUT_SECS);
+    let mut builder = reqwest::Client::builder()
+        .timeout(Duration::from_secs(request_timeout))
+        .connect_timeout(Duration::from_secs(connect_timeout))
+        .user_agent(config.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT));
+    // Without explicit proxies reqwest falls back to the HTTP_PROXY/HTTPS_PROXY environment variables
+    if let Some(proxy) = &config.http_proxy {
+        let proxy = parse_proxy_url(proxy).map_err(|e| anyhow::anyhow!("Invalid http_proxy: {}", e))?;
+        builder = builder.proxy(reqwest::Proxy::http(&proxy)?);
+    }
+    if let Some(proxy) = &config.https_proxy {
+        let proxy = parse_proxy_url(proxy).map_err(|e| anyhow::anyhow!("Invalid https_proxy: {}", e))?;
+        builder = builder.proxy(reqwest::Proxy::https(&proxy)?);
+    }
+    if config.danger_accept_invalid_certs.unwrap_or(false) {
+        warn!("TLS certificate verification is disabled (danger_accept_invalid_certs), connections can be intercepted");
+        builder = builder.tls_danger_accept_invalid_certs(true);
+    }
+    Ok(builder.build()?)
+}
+
+/// Placeholders accepted in download filename templates.
+pub const FILENAME_TEMPLATE_FIELDS: &[&str] = &["album", "index", "id", "name", "stem", "ext", "slug"];
+
+/// Checks that every `{...}` in a download filename template is a known placeholder.
+pub fn validate_filename_template(template: &str) -> Result<()> {
+    if template.trim().is_empty() {
+        return Err(anyhow::anyhow!("Filename template is empty"));
+    }
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..].find('}').ok_or_else(|| anyhow::anyhow!("Unclosed '{{' in filename template '{}'", template))?;
+        let field = &rest[start + 1..start + end];
+        if !FILENAME_TEMPLATE_FIELDS.contains(&field) {
+            return Err(anyhow::anyhow!("Unknown placeholder '{{{}}}' in filename template, expected one of {}", field,
+                FILENAME_TEMPLATE_FIELDS.iter().map(|f| format!("{{{}}}", f)).collect::<Vec<_>>().join(", ")));
+        }
+        rest = &rest[start + end + 1..];
+    }
+    Ok(())
+}
+
+/// Fills in a download filename template, returning a path relative to the output directory.
+/// `/` in the template starts a subdirectory; empty, `.` and `..` components are dropped so the
+/// result can't leave the output directory.
+pub fn expand_filename_template(template: &str, file: &AlbumFile, index: usize) -> PathBuf {
+    // Values are sanitized before they're filled in, so a name can't add directories of its own
+    let name = sanitize_filename(&file.original);
+    if name != file.original {
+        warn!("Saving '{}' as '{}', the name isn't safe to use as a file name", file.original, name);
+    }
+    let path = Path::new(&name);
+    // One pass over the template, so a value containing `{name}` or the like isn't expanded again
+    let mut expanded = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}').map(|end| start + end) else {
+            break;
+        };
+        expanded.push_str(&rest[..start]);
+        match &rest[start + 1..end] {
+            "album" => expanded.push_str(&sanitize_filename(&file.album)),
+            "index" => expanded.push_str(&format!("{:03}", index)),
+            "id" => expanded.push_str(&file.id.to_string()),
+            "name" => expanded.push_str(&name),
+            "stem" => expanded.push_str(&path.file_stem().unwrap_or_default().to_string_lossy()),
+            "ext" => expanded.push_str(&path.extension().unwrap_or_default().to_string_lossy()),
+            "slug" => expanded.push_str(&sanitize_filename(&file.slug)),
+            _ => expanded.push_str(&rest[start..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+    expanded.push_str(rest);
+
+    let mut result = PathBuf::new();
+    for component in expanded.split(['/', '\\']) {
+        let component = sanitize_filename(component);
+        if !component.is_empty() {
+            result.push(component);
+        }
+    }
+    if result.as_os_str().is_empty() {
+        result.push(file.id.to_string());
+    }
+    result
+}
+
+/// Makes `name` safe to use as a single file name on any platform: path separators, characters
+/// Windows doesn't allow and control characters become `_`, and reserved device names such as
+/// `CON` get a `_` prefix. Trailing dots and spaces are removed, so `.` and `..` become empty.
+pub fn sanitize_filename(name: &str) -> String {
+    const RESERVED: &[&str] = &[
+        "CON", "PRN", "AUX", "NUL",
+        "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+        "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+    ];
+    let replaced: String = name
+        .chars()
+        .map(|c| if c.is_control() || matches!(c, '/' | '\\' | '<' | '>' | ':' | '"' | '|' | '?' | '*') { '_' } else { c })
+        .collect();
+    let trimmed = replaced.trim().trim_end_matches(['.', ' ']);
+    // Windows treats `CON.txt` like `CON`, so only the part before the first dot matters
+    let base = trimmed.split('.').next().unwrap_or_default();
+    if RESERVED.iter().any(|r| r.eq_ignore_ascii_case(base)) {
+        format!("_{}", trimmed)
+    } else {
+        trimmed.to_string()
+    }
+}
+
+/// Human-readable size with one decimal, e.g. `1.5 MB`.
+pub fn format_size(size: u64) -> String {
+    if size >= 1024 * 1024 * 1024 {
+        format!("{:.1} GB", size as f64 / (1024.0 * 1024.0 * 1024.0))
+    } else if size >= 1024 * 1024 {
+        format!("{:.1} MB", size as f64 / (1024.0 * 1024.0))
+    } else if size >= 1024 {
+        format!("{:.1} KB", size as f64 / 1024.0)
+    } else {
+        format!("{} B", size)
+    }
+}
+
+/// Validates a proxy URL. Supports http, https and socks5 proxies; socks5h resolves names through the proxy.
+pub fn parse_proxy_url(url: &str) -> Result<String> {
+    let parsed = reqwest::Url::parse(url.trim()).map_err(|e| anyhow::anyhow!("Invalid proxy URL '{}': {}", url, e))?;
+    if !matches!(parsed.scheme(), "http" | "https" | "socks5" | "socks5h") {
+        return Err(anyhow::anyhow!("Invalid proxy URL '{}': scheme must be http, https, socks5 or socks5h", url));
+    }
+    if parsed.host_str().is_none() {
+        return Err(anyhow::anyhow!("Invalid proxy URL '{}': missing host", url));
+    }
+    Ok(url.trim().to_string())
+}
+
+/// Validates a configured base URL and strips any trailing slash so endpoints can be appended.
+pub fn parse_base_url(url: &str) -> Result<String> {
+    let parsed = reqwest::Url::parse(url.trim()).map_err(|e| anyhow::anyhow!("Invalid base URL '{}': {}", url, e))?;
+    if parsed.scheme() != "http" && parsed.scheme() != "https" {
+        return Err(anyhow::anyhow!("Invalid base URL '{}': scheme must be http or https", url));
+    }
+    if parsed.host_str().is_none() {
+        return Err(anyhow::anyhow!("Invalid base URL '{}': missing host", url));
+    }
+    Ok(parsed.as_str().trim_end_matches('/').to_string())
+}
+
+/// Tells album URLs (`/a/<slug>`) from file URLs (`/f/<slug>`) without fetching anything.
+/// Query strings and fragments are ignored.
+pub fn parse_bunkr_url(url: &str) -> Result<BunkrUrl> {
+    let parsed = reqwest::Url::parse(url.trim()).map_err(|e| anyhow::anyhow!("Invalid URL '{}': {}", url, e))?;
+    if parsed.scheme() != "http" && parsed.scheme() != "https" {
+        return Err(anyhow::anyhow!("Invalid URL '{}': scheme must be http or https", url));
+    }
+    let segments: Vec<&str> = parsed.path_segments().map(|s| s.filter(|s| !s.is_empty()).collect()).unwrap_or_default();
+    match segments.as_slice() {
+        ["a", slug, ..] => Ok(BunkrUrl::Album { slug: slug.to_string() }),
+        ["f", slug, ..] => Ok(BunkrUrl::File { slug: slug.to_string() }),
+        _ => Err(anyhow::anyhow!("Unsupported URL: {} (expected an album /a/... or file /f/... link)", url)),
+    }
+}
+
+/// Trims the whitespace a pasted or piped token tends to pick up and rejects tokens that can't be
+/// valid, so they fail with a clear message instead of "Invalid API token" from the server.
+pub fn normalize_token(token: &str) -> Result<String> {
+    let token = token.trim();
+    if token.is_empty() {
+        return Err(anyhow::anyhow!("API token is empty"));
+    }
+    if token.chars().any(char::is_whitespace) {
+        return Err(anyhow::anyhow!("API token contains whitespace, check that it was copied correctly"));
+    }
+    // Sent as a header, which only allows visible ASCII
+    if !token.chars().all(|c| c.is_ascii_graphic()) {
+        return Err(anyhow::anyhow!("API token contains characters that aren't allowed in a token"));
+    }
+    Ok(token.to_string())
+}
+
+/// Keyring entry holding the API token. The default profile keeps the original entry name.
 #[cfg(feature = "cli")]
-pub fn get_token(cli_token: Option<String>) -> Result<String> {
+pub fn token_entry(profile: Option<&str>) -> Result<Entry> {
+    let user = match profile {
+        Some(profile) if profile != "default" => format!("api_token_{}", profile),
+        _ => "api_token".to_string(),
+    };
+    Ok(Entry::new("bunkr_client", &user)?)
+}
+
+/// Finds the API token, trying `--token`, then `BUNKR_TOKEN`, then the keyring, then `token_file`.
+#[cfg(feature = "cli")]
+pub fn get_token(cli_token: Option<String>, profile: Option<&str>, token_file: Option<&str>) -> Result<String> {
+    let from = |source: &str, token: &str| normalize_token(token).map_err(|e| anyhow::anyhow!("{} (from {})", e, source));
     if let Some(t) = cli_token {
-        Ok(t)
-    } else {
-        let entry = Entry::new("bunkr_client", "api_token")?;
-        entry.get_password().map_err(|_| anyhow::anyhow!("No token provided and none saved. Use --token or save one with save-token command."))
+        return from("--token", &t);
     }
+    let mut tried = vec!["--token: not given".to_string()];
+
+    match std::env::var("BUNKR_TOKEN") {
+        Ok(t) if !t.trim().is_empty() => return from("BUNKR_TOKEN", &t),
+        _ => tried.push("BUNKR_TOKEN: not set".to_string()),
+    }
+
+    match token_entry(profile).and_then(|entry| Ok(entry.get_password()?)) {
+        Ok(t) => return from("keyring", &t),
+        Err(e) => tried.push(format!("keyring: {}", e)),
+    }
+
+    match token_file {
+        Some(path) => match std::fs::read_to_string(path) {
+            Ok(t) if !t.trim().is_empty() => return from(path, &t),
+            Ok(_) => tried.push(format!("token_file: {} is empty", path)),
+            Err(e) => tried.push(format!("token_file: failed to read {}: {}", path, e)),
+        },
+        None => tried.push("token_file: not configured".to_string()),
+    }
+
+    Err(anyhow::anyhow!(
+        "No API token found. Tried:\n  {}\nUse --token, set BUNKR_TOKEN, save one with the save-token command or set token_file.",
+        tried.join("\n  ")
+    ))
 }
 
 #[cfg(not(feature = "cli"))]
-pub fn get_token(_cli_token: Option<String>) -> Result<String> {
+pub fn get_token(_cli_token: Option<String>, _profile: Option<&str>, _token_file: Option<&str>) -> Result<String> {
     Err(anyhow::anyhow!("CLI feature is not enabled."))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Component;
+
+    fn album_file(original: &str, album: &str) -> AlbumFile {
+        AlbumFile {
+            id: 42,
+            original: original.to_string(),
+            album: album.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Whether `path` joined onto an output directory stays inside it.
+    fn stays_inside(path: &Path) -> bool {
+        !path.as_os_str().is_empty() && path.components().all(|c| matches!(c, Component::Normal(_)))
+    }
+
+    #[test]
+    fn sanitize_filename_removes_traversal() {
+        let name = sanitize_filename("../../etc/passwd");
+        assert_eq!(name, ".._.._etc_passwd");
+        assert!(stays_inside(Path::new(&name)));
+        assert_eq!(sanitize_filename(".."), "");
+        assert_eq!(sanitize_filename("."), "");
+    }
+
+    #[test]
+    fn sanitize_filename_replaces_separators() {
+        assert_eq!(sanitize_filename("a/b\\c"), "a_b_c");
+        assert_eq!(sanitize_filename("what?<now>.txt"), "what__now_.txt");
+    }
+
+    #[test]
+    fn sanitize_filename_prefixes_reserved_names() {
+        assert_eq!(sanitize_filename("CON.txt"), "_CON.txt");
+        assert_eq!(sanitize_filename("lpt1"), "_lpt1");
+        assert_eq!(sanitize_filename("CONSOLE.txt"), "CONSOLE.txt");
+    }
+
+    #[test]
+    fn sanitize_filename_trims_trailing_dots_and_spaces() {
+        assert_eq!(sanitize_filename("video.mp4..."), "video.mp4");
+        assert_eq!(sanitize_filename("  notes. . "), "notes");
+    }
+
+    #[test]
+    fn template_drops_parent_directories() {
+        let path = expand_filename_template("{album}/../{name}", &album_file("x.jpg", "Trip"), 1);
+        assert_eq!(path, Path::new("Trip").join("x.jpg"));
+        assert!(stays_inside(&path));
+    }
+
+    #[test]
+    fn template_keeps_malicious_names_in_one_component() {
+        let file = album_file("../../etc/passwd", "../..");
+        for template in ["{name}", "{album}/{index}_{name}", "{album}/{stem}.{ext}"] {
+            let path = expand_filename_template(template, &file, 3);
+            assert!(stays_inside(&path), "{} expanded to {}", template, path.display());
+            assert!(Path::new("/downloads").join(&path).starts_with("/downloads"));
+        }
+    }
+
+    #[test]
+    fn template_that_expands_to_nothing_uses_the_id() {
+        assert_eq!(expand_filename_template("{album}/..", &album_file("x.jpg", ""), 1), Path::new("42"));
+    }
+
+    #[test]
+    fn template_values_are_not_expanded_again() {
+        let file = album_file("{slug} {ext}.jpg", "{name}");
+        let path = expand_filename_template("{album}/{name}", &file, 1);
+        assert_eq!(path, Path::new("{name}").join("{slug} {ext}.jpg"));
+    }
+
+    #[test]
+    fn template_keeps_unknown_and_unclosed_braces() {
+        let path = expand_filename_template("{index}{x}_{name", &album_file("x.jpg", "Trip"), 2);
+        assert_eq!(path, Path::new("002{x}_{name"));
+    }
+
+    #[test]
+    fn parses_album_and_file_urls() {
+        assert_eq!(parse_bunkr_url("https://bunkr.cr/a/AbC123").unwrap(), BunkrUrl::Album { slug: "AbC123".to_string() });
+        assert_eq!(parse_bunkr_url("https://bunkr.cr/f/video-xyz").unwrap(), BunkrUrl::File { slug: "video-xyz".to_string() });
+        assert_eq!(parse_bunkr_url("  http://bunkr.si/a/slug  ").unwrap(), BunkrUrl::Album { slug: "slug".to_string() });
+    }
+
+    #[test]
+    fn bunkr_url_ignores_query_fragment_and_trailing_slash() {
+        let album = BunkrUrl::Album { slug: "slug".to_string() };
+        assert_eq!(parse_bunkr_url("https://bunkr.cr/a/slug?advanced=1").unwrap(), album);
+        assert_eq!(parse_bunkr_url("https://bunkr.cr/a/slug#files").unwrap(), album);
+        assert_eq!(parse_bunkr_url("https://bunkr.cr/a/slug/").unwrap(), album);
+        assert_eq!(parse_bunkr_url("https://bunkr.cr/a/slug/?page=2#top").unwrap(), album);
+    }
+
+    #[test]
+    fn bunkr_url_rejects_other_schemes() {
+        assert!(parse_bunkr_url("ftp://bunkr.cr/a/slug").is_err());
+        assert!(parse_bunkr_url("file:///a/slug").is_err());
+        assert!(parse_bunkr_url("bunkr.cr/a/slug").is_err());
+    }
+
+    #[test]
+    fn token_is_trimmed() {
+        assert_eq!(normalize_token("  abc123\n").unwrap(), "abc123");
+        assert_eq!(normalize_token("\tabc123\r\n").unwrap(), "abc123");
+    }
+
+    #[test]
+    fn empty_token_is_rejected() {
+        assert!(normalize_token("").is_err());
+        assert!(normalize_token(" \n\t").is_err());
+    }
+
+    #[test]
+    fn token_with_inner_whitespace_is_rejected() {
+        let error = normalize_token("abc 123").unwrap_err();
+        assert!(error.to_string().contains("whitespace"));
+        assert!(normalize_token("abc\n123").is_err());
+    }
+
+    #[test]
+    fn non_ascii_token_is_rejected() {
+        assert!(normalize_token("abcé123").is_err());
+        assert!(normalize_token("abc\u{a0}123").is_err());
+    }
+
+    #[test]
+    fn bunkr_url_rejects_unsupported_paths() {
+        for url in ["https://bunkr.cr/", "https://bunkr.cr/a/", "https://bunkr.cr/v/slug", "https://bunkr.cr/album/a/slug"] {
+            assert!(parse_bunkr_url(url).is_err(), "{} was accepted", url);
+        }
+    }
+}
@@ -6,8 +6,9 @@ pub mod preprocess;
 pub mod ui;
 
 // Re-export main types for easier use
-pub use core::uploader::BunkrUploader;
+pub use core::uploader::{BunkrUploader, UploadOptions};
 #[cfg(feature = "download")]
 pub use core::downloader::BunkrDownloader;
 pub use core::types::*;
+pub use core::utils::parse_bunkr_url;
 pub use config::config::Config;
@@ -2,19 +2,30 @@
 use clap::{Parser, Subcommand};
 #[cfg(feature = "cli")]
 use bunkr_client::BunkrUploader;
-#[cfg(feature = "ui")]
+#[cfg(feature = "cli")]
+use bunkr_client::core::cache::UploadCache;
+#[cfg(feature = "cli")]
+use bunkr_client::core::utils::format_size;
+#[cfg(all(feature = "cli", feature = "ui"))]
 use bunkr_client::ui::ui::{UIState, start_ui, stop_ui};
-#[cfg(feature = "ui")]
+#[cfg(all(feature = "cli", feature = "ui"))]
 use bunkr_client::ui::ui::OperationStatus;
 #[cfg(feature = "cli")]
-#[cfg(not(feature = "ui"))]
-use bunkr_client::core::types::UIState;
+use bunkr_client::core::progress::{LineProgress, ProgressSink};
+#[cfg(feature = "cli")]
+use bunkr_client::core::types::{FailedOperationInfo, ProgressEvent};
 #[cfg(feature = "cli")]
 use anyhow::Result;
 #[cfg(feature = "cli")]
-use keyring::Entry;
+use sha2::{Digest, Sha256};
 #[cfg(feature = "cli")]
-use std::{path::Path, sync::{Arc, Mutex}, io::Write, fs::OpenOptions};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+#[cfg(feature = "cli")]
+use tracing::debug;
+#[cfg(feature = "cli")]
+use std::{collections::{HashMap, HashSet}, path::{Path, PathBuf}, sync::Arc, io::{IsTerminal, Write}, fs::OpenOptions};
+#[cfg(all(feature = "cli", feature = "ui"))]
+use std::sync::Mutex;
 
 #[cfg(feature = "cli")]
 pub fn get_styles() -> clap::builder::Styles {
@@ -66,9 +77,131 @@ struct Cli {
     #[arg(short = 'n', long)]
     album_name: Option<String>,
 
+    /// Create the --album-name album if there's no album with that name yet
+    #[arg(long)]
+    create_album: bool,
+
     #[arg(short = 'b', long, global = true)]
     batch_size: Option<usize>,
 
+    /// Walk directories recursively
+    #[arg(short = 'r', long)]
+    recursive: bool,
+
+    /// Include symlinked files and directories found in directories. Listed paths are always followed
+    #[arg(long)]
+    follow_symlinks: bool,
+
+    /// Upload each directory's files into an album named after the folder, creating it if needed
+    #[arg(long, conflicts_with_all = ["album_id", "album_name"])]
+    album_per_dir: bool,
+
+    /// Only upload files found in directories that match this glob (repeatable)
+    #[arg(long)]
+    include: Vec<String>,
+
+    /// Skip files found in directories that match this glob (repeatable)
+    #[arg(long)]
+    exclude: Vec<String>,
+
+    /// Order to start files in: input, largest-first, smallest-first or interleaved
+    #[arg(long)]
+    schedule: Option<bunkr_client::UploadSchedule>,
+
+    /// Cap the combined upload speed, in bytes per second or as a size such as 2MB
+    #[arg(long, value_name = "RATE")]
+    limit_rate: Option<String>,
+
+    /// Where to upload: bunkr (the default), or local:/path to copy the files that would be uploaded into a directory
+    #[arg(long, value_name = "BACKEND")]
+    backend: Option<String>,
+
+    /// Split or reject files above this size, e.g. 500MB. Can't exceed the server's limit
+    #[arg(long, value_name = "SIZE")]
+    max_file_size: Option<String>,
+
+    /// Check each upload against the stored file: size (HEAD request) or hash (also compares the first 64 KiB)
+    #[arg(long, num_args = 0..=1, default_missing_value = "size", require_equals = true)]
+    verify: Option<bunkr_client::VerifyMode>,
+
+    /// Upload files whose name was already used in the batch as "name (2).ext" and so on
+    #[arg(long)]
+    rename_duplicates: bool,
+
+    /// Skip files whose name is already in the target album (case-insensitive)
+    #[arg(long)]
+    skip_existing: bool,
+
+    /// With --skip-existing, only skip files whose size matches too
+    #[arg(long, requires = "skip_existing")]
+    match_size: bool,
+
+    /// Skip files whose content is identical to another file in the batch
+    #[arg(long)]
+    dedupe_content: bool,
+
+    /// Reuse the URL of files uploaded in earlier runs with the same content, and remember new uploads
+    #[arg(long)]
+    use_cache: bool,
+
+    /// Don't show progress at all. Without a terminal, progress is printed line by line instead of the TUI
+    #[arg(short = 'q', long, global = true)]
+    quiet: bool,
+
+    /// Show more log output (-v for info, -vv for debug). RUST_LOG takes precedence
+    #[arg(short = 'v', long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Named profile with its own saved token and config file
+    #[arg(long, global = true)]
+    profile: Option<String>,
+
+    /// Config file to use instead of the default location (also settable via BUNKR_CONFIG)
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Session cookie sent when downloading, e.g. "session=...", for private albums (also settable via BUNKR_COOKIE)
+    #[arg(long, global = true)]
+    cookie: Option<String>,
+
+    /// Upload as temporary files that expire after this age (one of the server's temporaryUploadAges)
+    #[arg(long)]
+    age: Option<i64>,
+
+    /// Stop the batch at the first failed file
+    #[arg(long)]
+    fail_fast: bool,
+
+    /// Upload files as they are for this run, without video splitting or preprocess_command
+    #[arg(long, conflicts_with = "preprocess")]
+    no_preprocess: bool,
+
+    /// Split videos over the size limit for this run, even if preprocess_videos is off
+    #[arg(long)]
+    preprocess: bool,
+
+    /// Start even if the server reports maintenance mode
+    #[arg(long, global = true)]
+    force: bool,
+
+    /// File to write failed uploads to, replaced on each run
+    #[arg(long, global = true)]
+    failures_file: Option<PathBuf>,
+
+    /// Append to the failures file instead of replacing it
+    #[arg(long)]
+    append_failures: bool,
+
+    /// Don't append successful uploads to the results file
+    #[arg(long)]
+    no_results_file: bool,
+
+    /// Read more files or directories from a manifest, one per line. Blank lines and lines starting
+    /// with # are skipped; an optional tab-separated second column names the album to upload into
+    #[arg(long, value_name = "MANIFEST")]
+    from_file: Option<PathBuf>,
+
+    /// Files or directories to upload. Use path::name to upload a file under a different name
     paths: Vec<String>,
 
     #[command(subcommand)]
@@ -90,18 +223,85 @@ enum Commands {
         #[arg(short, long, default_value = "true")]
         public: bool,
     },
+    /// Edit an existing album's settings
+    EditAlbum {
+        album_id: i64,
+        #[arg(short, long)]
+        description: Option<String>,
+        #[arg(long)]
+        download: Option<bool>,
+        #[arg(long)]
+        public: Option<bool>,
+    },
+    /// Move uploaded files into another album, or out of their album with --remove
+    MoveFile {
+        /// Ids of the files to move
+        #[arg(required = true)]
+        file_ids: Vec<i64>,
+        /// Album to move the files into
+        #[arg(long, required_unless_present = "remove", conflicts_with = "remove")]
+        to: Option<i64>,
+        /// Take the files out of their album instead
+        #[arg(long)]
+        remove: bool,
+    },
+    /// Change the name an uploaded file is shown under
+    RenameFile {
+        file_id: i64,
+        new_name: String,
+    },
+    /// Check that the token is valid and the server is accepting uploads
+    Verify,
+    /// Show the account, permissions and retention periods for the active token
+    Whoami,
     /// Download files from an album
     Download {
         /// One or more album/file URLs to download
         album_urls: Vec<String>,
         #[arg(short, long)]
         output_dir: Option<String>,
+        /// Where to save each file inside the output directory, e.g. "{album}/{index}_{name}".
+        /// Placeholders: {album}, {index}, {id}, {name}, {stem}, {ext}, {slug}
+        #[arg(long)]
+        template: Option<String>,
+    },
+    /// Print the direct download URL of each file at an album or file URL, without downloading
+    GetUrl {
+        /// Album or file URL
+        url: String,
+    },
+    /// Copy a Bunkr album or file into one of your albums by downloading and re-uploading it
+    Mirror {
+        /// Album or file URL to copy
+        source_url: String,
+        /// Album to upload into, created if it doesn't exist
+        #[arg(short, long)]
+        album_name: String,
+    },
+    /// Upload the files listed in a failures file from a previous run
+    RetryFailures {
+        /// Failures file to read, JSON lines or the failed_uploads.txt format. Defaults to the JSON-lines copy of the failures file
+        file: Option<PathBuf>,
     },
     /// Manage configuration
     Config {
         #[command(subcommand)]
         action: ConfigAction,
     },
+    /// Manage the upload cache used by --use-cache
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+}
+
+#[cfg(feature = "cli")]
+#[derive(Subcommand)]
+enum CacheAction {
+    /// Forget every cached upload
+    Clear,
+    /// Forget uploads whose file was deleted or changed since
+    Prune,
 }
 
 #[cfg(feature = "cli")]
@@ -112,57 +312,978 @@ enum ConfigAction {
         /// Specific key to get, if omitted get all
         key: Option<String>,
     },
-    /// Set configuration value
+    /// Set configuration value, use "default" to restore the default
     Set {
         key: String,
         value: String,
     },
+    /// Restore a single key to its default value
+    Unset {
+        key: String,
+    },
+    /// Restore every key to its default value
+    Reset,
+    /// Write every key to a JSON file, for copying settings to another machine
+    Export {
+        path: PathBuf,
+    },
+    /// Set keys from a JSON file written by `config export`
+    Import {
+        path: PathBuf,
+    },
+}
+
+#[cfg(feature = "cli")]
+fn print_config_changes(previous: &bunkr_client::Config, current: &bunkr_client::Config) {
+    let changes = previous.diff(current);
+    if changes.is_empty() {
+        println!("Config unchanged.");
+        return;
+    }
+    for (key, old, new) in changes {
+        println!("{}: {} -> {}", key, old, new);
+    }
+}
+
+/// Include/exclude globs for files discovered in directories. Explicitly listed files are never filtered.
+#[cfg(feature = "cli")]
+struct FileFilter {
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+}
+
+#[cfg(feature = "cli")]
+impl FileFilter {
+    fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        Ok(Self {
+            include: Self::build(include)?,
+            exclude: Self::build(exclude)?,
+        })
+    }
+
+    fn build(patterns: &[String]) -> Result<Option<GlobSet>> {
+        if patterns.is_empty() {
+            return Ok(None);
+        }
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            builder.add(Glob::new(pattern).map_err(|e| anyhow::anyhow!("Invalid glob '{}': {}", pattern, e))?);
+        }
+        Ok(Some(builder.build()?))
+    }
+
+    /// Patterns are matched against both the file name and the full path.
+    fn allows(&self, path: &Path) -> bool {
+        let matches = |set: &GlobSet| set.is_match(path) || path.file_name().is_some_and(|name| set.is_match(name));
+        self.include.as_ref().is_none_or(matches) && !self.exclude.as_ref().is_some_and(matches)
+    }
+}
+
+/// Hashes the first and last 64 KiB of a file, cheap enough to run on every candidate.
+#[cfg(feature = "cli")]
+fn quick_hash(path: &str, size: u64) -> Result<Vec<u8>> {
+    use std::io::{Read, Seek, SeekFrom};
+    const SAMPLE: u64 = 64 * 1024;
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; SAMPLE as usize];
+    let n = file.read(&mut buf)?;
+    hasher.update(&buf[..n]);
+    if size > SAMPLE {
+        file.seek(SeekFrom::Start(size.saturating_sub(SAMPLE).max(SAMPLE)))?;
+        let n = file.read(&mut buf)?;
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher.finalize().to_vec())
+}
+
+#[cfg(feature = "cli")]
+fn full_hash(path: &str) -> Result<Vec<u8>> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(hasher.finalize().to_vec())
+}
+
+/// Drops files whose content matches an earlier file, keeping the first occurrence.
+/// Candidates are matched on size and a quick hash, then confirmed with a full hash.
+#[cfg(feature = "cli")]
+fn dedupe_by_content(files: Vec<String>) -> Result<(Vec<String>, usize)> {
+    let mut kept: Vec<String> = Vec::with_capacity(files.len());
+    let mut by_quick_key: HashMap<(u64, Vec<u8>), Vec<usize>> = HashMap::new();
+    let mut full_hashes: HashMap<usize, Vec<u8>> = HashMap::new();
+    let mut duplicates = 0;
+    for file in files {
+        let size = std::fs::metadata(&file)?.len();
+        let key = (size, quick_hash(&file, size)?);
+        let candidates = by_quick_key.get(&key).cloned().unwrap_or_default();
+        let mut is_duplicate = false;
+        if !candidates.is_empty() {
+            let hash = full_hash(&file)?;
+            for index in candidates {
+                let existing = match full_hashes.entry(index) {
+                    std::collections::hash_map::Entry::Occupied(e) => e.into_mut(),
+                    std::collections::hash_map::Entry::Vacant(e) => e.insert(full_hash(&kept[index])?),
+                };
+                if *existing == hash {
+                    is_duplicate = true;
+                    break;
+                }
+            }
+        }
+        if is_duplicate {
+            duplicates += 1;
+        } else {
+            by_quick_key.entry(key).or_default().push(kept.len());
+            kept.push(file);
+        }
+    }
+    Ok((kept, duplicates))
 }
 
 #[cfg(feature = "cli")]
-fn collect_all_files(paths: &[String]) -> Result<Vec<String>> {
+struct CollectedFiles {
+    /// Canonical paths, deduplicated, in first-seen order.
+    files: Vec<String>,
+    /// Files dropped by include/exclude patterns.
+    filtered: usize,
+    /// Paths that appeared more than once.
+    duplicates: usize,
+}
+
+/// Per-file settings given on the command line or in a manifest, keyed by canonical path to match
+/// what `collect_all_files` returns.
+#[cfg(feature = "cli")]
+#[derive(Default)]
+struct FileOverrides {
+    /// Name to upload the file as, from `path::name` arguments.
+    display_names: HashMap<String, String>,
+    /// Album to upload the file into by name, from the second column of a `--from-file` manifest.
+    albums: HashMap<String, String>,
+}
+
+/// What an `upload_paths` run did, for settings saved afterwards.
+#[cfg(feature = "cli")]
+struct UploadRun {
+    /// Album all files went to, when the run targeted a single album through the album options.
+    album_id: Option<String>,
+    failed: usize,
+}
+
+#[cfg(feature = "cli")]
+struct Manifest {
+    /// Paths that exist, in the order listed.
+    paths: Vec<String>,
+    /// `(path, album name)` for lines with a second column.
+    albums: Vec<(String, String)>,
+    /// Listed paths that don't exist.
+    missing: Vec<String>,
+}
+
+/// Reads a `--from-file` manifest: one `path` or `path<TAB>album name` per line.
+#[cfg(feature = "cli")]
+fn read_manifest(file: &Path) -> Result<Manifest> {
+    let content = std::fs::read_to_string(file)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", file.display(), e))?;
+    let mut manifest = Manifest { paths: vec![], albums: vec![], missing: vec![] };
+    for line in content.lines() {
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        let (path, album) = match line.split_once('\t') {
+            Some((path, album)) => (path.trim(), Some(album.trim()).filter(|a| !a.is_empty())),
+            None => (line.trim(), None),
+        };
+        if !Path::new(path).exists() {
+            manifest.missing.push(path.to_string());
+            continue;
+        }
+        if let Some(album) = album {
+            manifest.albums.push((path.to_string(), album.to_string()));
+        }
+        manifest.paths.push(path.to_string());
+    }
+    Ok(manifest)
+}
+
+/// Splits `path::name` arguments into the path and the name to upload it as. Display names are
+/// keyed by canonical path to match what `collect_all_files` returns.
+#[cfg(feature = "cli")]
+fn split_display_names(args: &[String]) -> Result<(Vec<String>, HashMap<String, String>)> {
+    let mut paths = Vec::with_capacity(args.len());
+    let mut names = HashMap::new();
+    for arg in args {
+        match arg.rsplit_once("::") {
+            Some((path, name)) if !Path::new(arg).exists() => {
+                if !Path::new(path).is_file() {
+                    return Err(anyhow::anyhow!("Invalid path: {} (a display name can only be given for a file)", path));
+                }
+                names.insert(std::fs::canonicalize(path)?.to_string_lossy().to_string(), name.to_string());
+                paths.push(path.to_string());
+            }
+            _ => paths.push(arg.clone()),
+        }
+    }
+    Ok((paths, names))
+}
+
+#[cfg(feature = "cli")]
+fn collect_all_files(paths: &[String], recursive: bool, follow_symlinks: bool, filter: &FileFilter) -> Result<CollectedFiles> {
     let mut files = vec![];
+    let mut filtered = 0;
+    let mut visited = HashSet::new();
     for path in paths {
         let p = Path::new(path);
         if p.is_file() {
             files.push(path.clone());
         } else if p.is_dir() {
-            let dir_files = std::fs::read_dir(p)?
-                .filter_map(|e| e.ok())
-                .filter(|e| e.path().is_file())
-                .map(|e| e.path().to_string_lossy().to_string())
-                .collect::<Vec<_>>();
+            let mut dir_files = vec![];
+            collect_dir(p, recursive, follow_symlinks, &mut visited, &mut dir_files)?;
+            let before = dir_files.len();
+            dir_files.retain(|f| filter.allows(Path::new(f)));
+            filtered += before - dir_files.len();
             files.extend(dir_files);
         } else {
             return Err(anyhow::anyhow!("Invalid path: {}", path));
         }
     }
-    Ok(files)
+
+    // The same file can be listed explicitly and also found through its directory
+    let mut seen = HashSet::new();
+    let mut unique = Vec::with_capacity(files.len());
+    let mut duplicates = 0;
+    for file in files {
+        let canonical = std::fs::canonicalize(&file)?;
+        let Some(canonical) = canonical.to_str().map(str::to_string) else {
+            eprintln!("Skipping {}: the path isn't valid UTF-8", canonical.display());
+            continue;
+        };
+        if seen.insert(canonical.clone()) {
+            unique.push(canonical);
+        } else {
+            duplicates += 1;
+        }
+    }
+    Ok(CollectedFiles { files: unique, filtered, duplicates })
+}
+
+/// Collects the files in `dir`, skipping hidden entries and, unless `follow_symlinks` is set,
+/// symlinks. Canonical paths of visited directories are tracked so symlink loops are only walked once.
+#[cfg(feature = "cli")]
+fn collect_dir(dir: &Path, recursive: bool, follow_symlinks: bool, visited: &mut HashSet<PathBuf>, files: &mut Vec<String>) -> Result<()> {
+    if !visited.insert(dir.canonicalize()?) {
+        return Ok(());
+    }
+    let mut entries: Vec<_> = std::fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .filter(|e| !e.file_name().to_string_lossy().starts_with('.'))
+        .collect();
+    entries.sort_by_key(|e| e.file_name());
+    for entry in entries {
+        let path = entry.path();
+        if !follow_symlinks && entry.file_type().is_ok_and(|t| t.is_symlink()) {
+            debug!("Skipping symlink {}", path.display());
+            continue;
+        }
+        if path.is_file() {
+            // Paths are passed around as strings, a lossy conversion would point at a file that doesn't exist
+            match path.to_str() {
+                Some(path) => files.push(path.to_string()),
+                None => eprintln!("Skipping {}: the path isn't valid UTF-8", path.display()),
+            }
+        } else if recursive && path.is_dir() {
+            collect_dir(&path, recursive, follow_symlinks, visited, files)?;
+        }
+    }
+    Ok(())
+}
+
+/// Groups files by their parent directory, named after the folder, keeping first-seen order.
+#[cfg(feature = "cli")]
+fn group_by_directory(files: Vec<String>) -> Vec<(String, Vec<String>)> {
+    let mut groups: Vec<(PathBuf, String, Vec<String>)> = vec![];
+    for file in files {
+        let parent = Path::new(&file).parent().unwrap_or(Path::new("."));
+        let parent = std::fs::canonicalize(if parent.as_os_str().is_empty() { Path::new(".") } else { parent })
+            .unwrap_or_else(|_| parent.to_path_buf());
+        if let Some(group) = groups.iter_mut().find(|(dir, _, _)| *dir == parent) {
+            group.2.push(file);
+        } else {
+            let name = parent.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| "root".to_string());
+            groups.push((parent, name, vec![file]));
+        }
+    }
+    groups.into_iter().map(|(_, name, files)| (name, files)).collect()
+}
+
+/// Where logs go while the TUI owns the terminal.
+#[cfg(feature = "cli")]
+fn log_file_path() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("bunkr_client.log")
+}
+
+/// The TUI needs a terminal to draw on; piped or scheduled runs fall back to line output.
+#[cfg(feature = "cli")]
+fn interactive(cli: &Cli) -> bool {
+    cfg!(feature = "ui") && !cli.quiet && std::io::stdout().is_terminal()
+}
+
+#[cfg(all(feature = "cli", feature = "ui"))]
+fn ui_refresh_interval(config: &bunkr_client::Config) -> std::time::Duration {
+    std::time::Duration::from_millis(config.ui_refresh_ms.unwrap_or(bunkr_client::config::config::DEFAULT_UI_REFRESH_MS))
+}
+
+/// Progress reporting for runs without the TUI: one line per file, or nothing with --quiet.
+#[cfg(feature = "cli")]
+fn line_progress(cli: &Cli) -> Option<Arc<dyn ProgressSink + Send + Sync>> {
+    if cli.quiet {
+        None
+    } else {
+        Some(Arc::new(LineProgress))
+    }
+}
+
+/// Sets up the tracing subscriber. Logs go to stderr, or to `log_file_path` when the TUI is
+/// running so they don't corrupt the screen.
+#[cfg(feature = "cli")]
+fn init_logging(verbose: u8, to_file: bool) -> Result<()> {
+    let level = match verbose {
+        0 => "warn",
+        1 => "info",
+        _ => "debug",
+    };
+    let filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new(format!("bunkr_client={}", level)));
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    if to_file {
+        let path = log_file_path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        builder.with_ansi(false).with_writer(std::sync::Mutex::new(file)).init();
+    } else {
+        builder.with_writer(std::io::stderr).init();
+    }
+    Ok(())
+}
+
+#[cfg(feature = "cli")]
+fn cache_file_path(config: &bunkr_client::Config) -> PathBuf {
+    config.cache_file.as_ref().map(PathBuf::from).unwrap_or_else(|| {
+        dirs::cache_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("bunkr_client_cache.json")
+    })
+}
+
+#[cfg(feature = "cli")]
+fn failures_file_path(cli: &Cli, config: &bunkr_client::Config) -> PathBuf {
+    cli.failures_file.clone()
+        .or_else(|| config.failures_file.as_ref().map(PathBuf::from))
+        .unwrap_or_else(|| PathBuf::from(bunkr_client::config::config::DEFAULT_FAILURES_FILE))
+}
+
+/// Reads failed paths from a JSON-lines failures file or the older `File: ..., Error: ...` text format.
+#[cfg(feature = "cli")]
+fn read_failed_paths(path: &Path) -> Result<Vec<String>> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+    let mut paths = vec![];
+    for line in content.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+        // Split parts were deleted after the run, so their original is retried and split again
+        if let Ok(failure) = serde_json::from_str::<FailedOperationInfo>(line) {
+            paths.push(failure.source.unwrap_or(failure.path));
+        } else if let Some((_, source)) = line.strip_prefix("File: ").and_then(|rest| rest.rsplit_once(", Source: ")) {
+            paths.push(source.to_string());
+        } else if let Some((file, _)) = line.strip_prefix("File: ").and_then(|rest| rest.split_once(", Error: ")) {
+            paths.push(file.to_string());
+        } else {
+            eprintln!("Skipping unrecognized line: {}", line);
+        }
+    }
+    let mut seen = HashSet::new();
+    paths.retain(|p| seen.insert(p.clone()));
+    Ok(paths)
+}
+
+/// Downloader for `config`, logged in with the `--cookie` or `BUNKR_COOKIE` session cookie if there is one.
+#[cfg(feature = "cli")]
+async fn new_downloader(cli: &Cli, config: &bunkr_client::Config) -> Result<bunkr_client::BunkrDownloader> {
+    let downloader = bunkr_client::BunkrDownloader::new(config).await?;
+    let cookie = cli.cookie.clone()
+        .or_else(|| std::env::var("BUNKR_COOKIE").ok())
+        .filter(|cookie| !cookie.trim().is_empty());
+    match cookie {
+        Some(cookie) => downloader.with_cookie(&cookie),
+        None => Ok(downloader),
+    }
+}
+
+/// Downloads `files` into `output_dir` behind the TUI (or line output), then lists the failures.
+#[cfg(feature = "cli")]
+async fn download_with_progress(
+    cli: &Cli,
+    #[cfg_attr(not(feature = "ui"), allow(unused_variables))] config: &bunkr_client::Config,
+    downloader: &bunkr_client::BunkrDownloader,
+    files: Vec<bunkr_client::AlbumFile>,
+    output_dir: &str,
+    batch_size: usize,
+) -> Result<()> {
+    #[cfg(feature = "ui")]
+    let ui_state = interactive(cli).then(|| {
+        let total_bytes: u64 = files.iter().map(|f| f.size as u64).sum();
+        Arc::new(Mutex::new(UIState::new(files.len(), None, total_bytes)))
+    });
+    #[cfg(feature = "ui")]
+    let progress = ui_state.clone().map(|s| s as Arc<dyn ProgressSink + Send + Sync>).or_else(|| line_progress(cli));
+    #[cfg(not(feature = "ui"))]
+    let progress = line_progress(cli);
+    #[cfg(feature = "ui")]
+    let ui = ui_state.clone().map(|state| start_ui(state, ui_refresh_interval(config)));
+
+    downloader.download_files(files, output_dir, batch_size, progress).await?;
+
+    // Print failed operations
+    #[cfg(feature = "ui")]
+    {
+        if let Some((ui_handle, running)) = ui {
+            stop_ui(ui_handle, running);
+        }
+
+        if let Some(ref state) = ui_state {
+            let state = state.lock().unwrap();
+            let failed: Vec<_> = state.all_operations.iter()
+                .filter_map(|(name, status)| {
+                    if let OperationStatus::Failed(info) = status {
+                        Some((name.clone(), info.clone()))
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+            if !failed.is_empty() {
+                println!("Failed downloads:");
+                for (name, info) in failed {
+                    println!("  {}: {} (size: {}, status: {:?})", name, info.error, info.file_size, info.status_code);
+                }
+            } else {
+                println!("All downloads completed successfully.");
+            }
+        } else {
+            println!("Download completed. Check for any errors above.");
+        }
+    }
+    #[cfg(not(feature = "ui"))]
+    println!("Download completed. Check for any errors above.");
+
+    Ok(())
+}
+
+/// Drops files whose upload name (and with `match_size`, size) is already in their target album.
+/// Returns the remaining groups and the skipped files with their sizes.
+#[cfg(feature = "cli")]
+async fn remove_existing(
+    uploader: &BunkrUploader,
+    groups: Vec<(Option<String>, Vec<String>)>,
+    display_names: &HashMap<String, String>,
+    match_size: bool,
+) -> Result<(Vec<(Option<String>, Vec<String>)>, Vec<(String, u64)>)> {
+    let mut remaining = Vec::with_capacity(groups.len());
+    let mut skipped = vec![];
+    for (album_id, files) in groups {
+        let Some(id) = album_id.as_deref() else {
+            println!("--skip-existing needs a target album, uploading all files.");
+            remaining.push((album_id, files));
+            continue;
+        };
+        let id: i64 = id.parse().map_err(|_| anyhow::anyhow!("Invalid album id: {}", id))?;
+        let existing: HashMap<String, i64> = uploader.get_album_files(id).await?
+            .into_iter()
+            .map(|f| (f.original.to_lowercase(), f.size))
+            .collect();
+        let mut upload = vec![];
+        for file in files {
+            let name = display_names.get(&file).cloned()
+                .unwrap_or_else(|| Path::new(&file).file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default());
+            let size = std::fs::metadata(&file).map(|m| m.len()).unwrap_or(0);
+            let exists = existing.get(&name.to_lowercase())
+                .is_some_and(|&album_size| !match_size || album_size == size as i64);
+            if exists {
+                skipped.push((file, size));
+            } else {
+                upload.push(file);
+            }
+        }
+        remaining.push((album_id, upload));
+    }
+    Ok((remaining, skipped))
+}
+
+#[cfg(feature = "cli")]
+fn print_upload_summary(uploaded: &[bunkr_client::UploadOutcome], failed: usize, elapsed: std::time::Duration) {
+    let bytes: u64 = uploaded.iter().map(|o| o.file_size).sum();
+    let seconds = elapsed.as_secs_f64();
+    let speed = if seconds > 0.0 { (bytes as f64 / seconds) as u64 } else { 0 };
+    let mut summary = format!(
+        "Uploaded {}/{} files ({}) in {:.1}s, {}/s on average.",
+        uploaded.len(), uploaded.len() + failed, format_size(bytes), seconds, format_size(speed),
+    );
+    if failed > 0 {
+        summary.push_str(&format!(" {} failed.", failed));
+    }
+    println!("{}", summary);
+}
+
+#[cfg(feature = "cli")]
+async fn find_or_create_album(uploader: &BunkrUploader, name: &str) -> Result<i64> {
+    if let Some(id) = uploader.get_album_by_name(name).await? {
+        return Ok(id);
+    }
+    let id = uploader.create_album(name.to_string(), None, true, true).await?;
+    println!("Created album '{}' with ID: {}", name, id);
+    Ok(id)
+}
+
+#[cfg(feature = "cli")]
+async fn upload_paths(
+    cli: &Cli,
+    config: &bunkr_client::Config,
+    all_files: Vec<String>,
+    batch_size: usize,
+    album_id: Option<String>,
+    album_name: Option<String>,
+    overrides: FileOverrides,
+) -> Result<UploadRun> {
+    // Only the TUI shows totals
+    #[cfg(feature = "ui")]
+    let total_bytes: u64 = all_files.iter()
+        .filter_map(|f| std::fs::metadata(f).ok().map(|m| m.len()))
+        .sum();
+    #[cfg(feature = "ui")]
+    let total_files = all_files.len();
+
+    let local_dir = match cli.backend.as_deref() {
+        None | Some("bunkr") => None,
+        Some(backend) => Some(backend.strip_prefix("local:").filter(|dir| !dir.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("Unknown backend '{}', expected bunkr or local:/path", backend))?),
+    };
+    let uploader = match local_dir {
+        Some(dir) => BunkrUploader::local(dir, config)?,
+        None => {
+            let token = bunkr_client::core::utils::get_token(cli.token.clone(), cli.profile.as_deref(), config.token_file.as_deref())?;
+            let uploader = BunkrUploader::new(token, config).await?;
+            if let Some(age) = config.default_upload_age {
+                uploader.validate_upload_age(age)?;
+            }
+            uploader
+        }
+    }.with_display_names(overrides.display_names.clone());
+
+    let mut target_album = None;
+    // Files the manifest routes to an album skip the album options below
+    let (routed, all_files): (Vec<String>, Vec<String>) = if local_dir.is_some() {
+        (vec![], all_files)
+    } else {
+        all_files.into_iter().partition(|f| overrides.albums.contains_key(f))
+    };
+    let mut groups: Vec<(Option<String>, Vec<String>)> = if let Some(dir) = local_dir {
+        if cli.album_per_dir || cli.skip_existing || album_id.is_some() || album_name.is_some() || !overrides.albums.is_empty() {
+            eprintln!("Albums aren't available with the local backend, copying everything into {}", dir);
+        }
+        vec![(None, all_files)]
+    } else if all_files.is_empty() {
+        vec![]
+    } else if cli.album_per_dir {
+        let mut groups = vec![];
+        for (dir_name, files) in group_by_directory(all_files) {
+            let id = find_or_create_album(&uploader, &dir_name).await?;
+            groups.push((Some(id.to_string()), files));
+        }
+        groups
+    } else {
+        let album_id = if let Some(name) = album_name {
+            if let Some(id) = uploader.get_album_by_name(&name).await? {
+                println!("Uploading to album: {}", name);
+                Some(id.to_string())
+            } else if cli.create_album {
+                Some(find_or_create_album(&uploader, &name).await?.to_string())
+            } else {
+                return Err(anyhow::anyhow!("Album '{}' not found, pass --create-album to create it", name));
+            }
+        } else if let Some(id) = album_id {
+            // Checked up front so a mistyped id fails before anything is uploaded
+            let parsed = id.parse::<i64>().map_err(|_| anyhow::anyhow!("Invalid album id '{}'", id))?;
+            let album = uploader.get_album(parsed).await?
+                .ok_or_else(|| anyhow::anyhow!("Album {} not found", id))?;
+            println!("Uploading to album: {}", album.name);
+            Some(id)
+        } else {
+            None
+        };
+        target_album = album_id.clone();
+        vec![(album_id, all_files)]
+    };
+    let mut routed_groups: Vec<(String, Vec<String>)> = vec![];
+    for file in routed {
+        let name = &overrides.albums[&file];
+        match routed_groups.iter_mut().find(|(album, _)| album == name) {
+            Some((_, files)) => files.push(file),
+            None => routed_groups.push((name.clone(), vec![file])),
+        }
+    }
+    for (name, files) in routed_groups {
+        let id = find_or_create_album(&uploader, &name).await?;
+        groups.push((Some(id.to_string()), files));
+    }
+    let (groups, skipped) = if cli.skip_existing && local_dir.is_none() {
+        remove_existing(&uploader, groups, &overrides.display_names, cli.match_size).await?
+    } else {
+        (groups, vec![])
+    };
+    let mut cache = if cli.use_cache && local_dir.is_none() {
+        Some(UploadCache::load(cache_file_path(config))?)
+    } else {
+        None
+    };
+    // (path, size, url) of files uploaded before, which aren't uploaded again
+    let mut cached = vec![];
+    let groups = match &cache {
+        Some(cache) => groups.into_iter()
+            .map(|(album_id, files)| {
+                let files = files.into_iter()
+                    .filter(|file| match cache.lookup(Path::new(file)) {
+                        Ok(Some(url)) => {
+                            cached.push((file.clone(), std::fs::metadata(file).map(|m| m.len()).unwrap_or(0), url));
+                            false
+                        }
+                        Ok(None) => true,
+                        Err(e) => {
+                            debug!("Upload cache lookup failed for {}: {}", file, e);
+                            true
+                        }
+                    })
+                    .collect();
+                (album_id, files)
+            })
+            .collect(),
+        None => groups,
+    };
+    #[cfg(feature = "ui")]
+    let ui_state = interactive(cli).then(|| {
+        let album_id = if groups.len() == 1 { groups[0].0.clone() } else { None };
+        Arc::new(Mutex::new(UIState::new(total_files, album_id, total_bytes)))
+    });
+    #[cfg(feature = "ui")]
+    let progress = ui_state.clone().map(|s| s as Arc<dyn ProgressSink + Send + Sync>).or_else(|| line_progress(cli));
+    #[cfg(not(feature = "ui"))]
+    let progress = line_progress(cli);
+    #[cfg(feature = "ui")]
+    if let Some(state) = &ui_state {
+        let mut state = state.lock().unwrap();
+        for (path, size) in &skipped {
+            state.add_skipped(path.clone(), *size, "already in album".to_string());
+        }
+        for (path, size, url) in &cached {
+            state.add_cached(path.clone(), *size, url.clone());
+        }
+        // Listed up front so an upload age can be picked before each file starts
+        for path in groups.iter().flat_map(|(_, files)| files) {
+            state.add_pending(path.clone(), std::fs::metadata(path).map(|m| m.len()).unwrap_or(0));
+        }
+        state.retention_periods = uploader.account().retentionPeriods.clone().unwrap_or_default();
+    }
+    #[cfg(feature = "ui")]
+    let ui = ui_state.clone().map(|state| start_ui(state, ui_refresh_interval(config)));
+
+    // Append successful uploads as they complete, so a crash mid-batch still leaves a record
+    let (events, results_writer) = if cli.no_results_file {
+        (None, None)
+    } else {
+        let results_path = config.results_file.clone()
+            .unwrap_or_else(|| bunkr_client::config::config::DEFAULT_RESULTS_FILE.to_string());
+        let mut results_file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&results_path)?;
+        let (tx, mut rx) = tokio::sync::mpsc::channel::<ProgressEvent>(256);
+        let writer = tokio::spawn(async move {
+            while let Some(event) = rx.recv().await {
+                if let ProgressEvent::Completed { path, url: Some(url), .. } = event {
+                    if let Err(e) = writeln!(results_file, "{}\t{}", path, url).and_then(|_| results_file.flush()) {
+                        eprintln!("Failed to write to {}: {}", results_path, e);
+                    }
+                }
+            }
+        });
+        (Some(tx), Some(writer))
+    };
+
+    if let Some(tx) = &events {
+        for (path, _, url) in &cached {
+            let _ = tx.send(ProgressEvent::Completed { path: path.clone(), url: Some(url.clone()), retries: 0 }).await;
+        }
+    }
+    let batch_files: HashSet<String> = groups.iter().flat_map(|(_, files)| files.iter().cloned()).collect();
+
+    let fail_fast = config.fail_fast.unwrap_or(false);
+    let files = groups.into_iter()
+        .flat_map(|(album_id, files)| files.into_iter().map(move |file| (file, album_id.clone())))
+        .collect();
+    let started = std::time::Instant::now();
+    let (uploaded, failures) = uploader.upload_files_to_albums(files, batch_size, progress.clone(), Some(config), events.clone()).await?;
+    let elapsed = started.elapsed();
+    drop(events);
+    if let Some(writer) = results_writer {
+        let _ = writer.await;
+    }
+
+    #[cfg(feature = "ui")]
+    let ui_shown = ui.is_some();
+    #[cfg(not(feature = "ui"))]
+    let ui_shown = false;
+    #[cfg(feature = "ui")]
+    if let Some((ui_handle, running)) = ui {
+        // Taken before the UI stops since leaving it clears the screen the totals were on
+        let summary = ui_state.as_ref().map(|state| state.lock().unwrap().summary());
+        stop_ui(ui_handle, running);
+        if let Some(summary) = summary {
+            println!("{}", summary);
+        }
+    }
+
+    if !skipped.is_empty() {
+        println!("Skipped {} file{} already in the album.", skipped.len(), if skipped.len() == 1 { "" } else { "s" });
+    }
+
+    if let Some(cache) = &mut cache {
+        if !cached.is_empty() {
+            println!("Reused {} earlier upload{} from the cache:", cached.len(), if cached.len() == 1 { "" } else { "s" });
+            for (path, _, url) in &cached {
+                println!("  {}: {}", path, url);
+            }
+        }
+        // Files that were split or converted upload as several files; only whole files are cached
+        let mut outcomes_per_file: HashMap<&str, usize> = HashMap::new();
+        for outcome in &uploaded {
+            *outcomes_per_file.entry(outcome.path.as_str()).or_default() += 1;
+        }
+        for outcome in &uploaded {
+            let Some(sha256) = &outcome.sha256 else { continue };
+            if batch_files.contains(&outcome.path) && outcomes_per_file[outcome.path.as_str()] == 1 {
+                if let Err(e) = cache.insert(Path::new(&outcome.path), sha256.clone(), outcome.url.clone()) {
+                    debug!("Failed to cache {}: {}", outcome.path, e);
+                }
+            }
+        }
+        if let Err(e) = cache.save() {
+            eprintln!("Failed to save the upload cache to {}: {}", cache.path().display(), e);
+        }
+    }
+
+    // Uploads that only went through after retries point at a flaky connection or an overloaded node
+    let retried: Vec<_> = uploaded.iter().filter(|o| o.retries > 0).collect();
+    if !retried.is_empty() {
+        let total: u32 = retried.iter().map(|o| o.retries).sum();
+        println!("{} of {} file(s) needed retries ({} in total); a smaller batch size may help.", retried.len(), uploaded.len(), total);
+        for outcome in &retried {
+            debug!("{} succeeded after {} retries", outcome.path, outcome.retries);
+        }
+    }
+
+    // Line output only reports files one at a time, so finish with the totals
+    if !ui_shown && !cli.quiet {
+        print_upload_summary(&uploaded, failures.len(), elapsed);
+    }
+
+    // Write the failed uploads to a file, plus a JSON-lines copy that retry-failures can read back.
+    // Each run replaces the previous failures unless --append-failures is given.
+    if !failures.is_empty() {
+        let failures_path = failures_file_path(cli, config);
+        let open = |path: &Path| OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(cli.append_failures)
+            .truncate(!cli.append_failures)
+            .open(path);
+        let mut failed_file = open(&failures_path)?;
+        let mut failed_json = open(&failures_path.with_extension("jsonl"))?;
+        writeln!(failed_file, "# Run at {}", httpdate::fmt_http_date(std::time::SystemTime::now()))?;
+        for failure in &failures {
+            write!(failed_file, "File: {}, Error: {}, Size: {}, Status: {:?}", failure.path, failure.error, failure.file_size, failure.status_code)?;
+            match &failure.source {
+                Some(source) => writeln!(failed_file, ", Source: {}", source)?,
+                None => writeln!(failed_file)?,
+            }
+            writeln!(failed_json, "{}", serde_json::to_string(failure)?)?;
+        }
+    }
+
+    if fail_fast {
+        if let Some(first) = failures.first() {
+            return Err(anyhow::anyhow!("Stopped after {} failed: {}", first.path, first.error));
+        }
+    }
+
+    Ok(UploadRun { album_id: target_album, failed: failures.len() })
+}
+
+/// Saves the album and batch size of a run without failures as the new defaults, when `remember_last` is on.
+#[cfg(feature = "cli")]
+fn remember_last_run(cli: &Cli, config_path: Option<&Path>, run: &UploadRun, batch_size: usize) -> Result<()> {
+    // Edit the file itself so environment and command line overrides aren't written back
+    let mut config = bunkr_client::Config::load_file(config_path)?;
+    let previous = config.clone();
+    config.default_batch_size = Some(batch_size);
+    if let Some(id) = &run.album_id {
+        config.default_album_id = Some(id.clone());
+        // The name would take precedence over the remembered id
+        config.default_album_name = None;
+    }
+    let changes = previous.diff(&config);
+    if changes.is_empty() {
+        return Ok(());
+    }
+    config.save(config_path)?;
+    if !cli.quiet {
+        for (key, old, new) in changes {
+            println!("Remembered {}: {} -> {}", key, old, new);
+        }
+    }
+    Ok(())
 }
 
 #[cfg(feature = "cli")]
 #[tokio::main]
 async fn main() -> Result<()> {
-    let cli = Cli::parse();
-    let config = bunkr_client::Config::load()?;
-    let batch_size = cli.batch_size.or_else(|| config.default_batch_size).unwrap_or(1);
-    let album_id = cli.album_id.or_else(|| config.default_album_id.clone());
-    let album_name = cli.album_name.or_else(|| config.default_album_name.clone());
+    let mut cli = Cli::parse();
+    let uses_tui = interactive(&cli)
+        && matches!(cli.command, None | Some(Commands::Download { .. }) | Some(Commands::Mirror { .. }) | Some(Commands::RetryFailures { .. }));
+    init_logging(cli.verbose, uses_tui)?;
+    let config_path = cli.config.clone().or_else(|| cli.profile.as_deref().map(bunkr_client::Config::profile_path));
+    let mut config = bunkr_client::Config::load(config_path.as_deref())?;
+    if cli.age.is_some() {
+        config.default_upload_age = cli.age;
+    }
+    if cli.force {
+        config.ignore_maintenance = Some(true);
+    }
+    if cli.fail_fast {
+        config.fail_fast = Some(true);
+    }
+    if cli.no_preprocess {
+        config.preprocess_videos = Some(false);
+        config.preprocess_command = None;
+    }
+    if cli.preprocess {
+        config.preprocess_videos = Some(true);
+    }
+    if let Some(rate) = &cli.limit_rate {
+        config.set_value("max_upload_bytes_per_sec", rate)?;
+    }
+    if let Some(size) = &cli.max_file_size {
+        config.set_value("max_file_size", size)?;
+    }
+    if cli.verify.is_some() {
+        config.verify_uploads = cli.verify;
+    }
+    if cli.rename_duplicates {
+        config.rename_duplicates = Some(true);
+    }
+    if cli.schedule.is_some() {
+        config.upload_schedule = cli.schedule;
+    }
+    // Printed before the TUI starts so it can't be missed, and on every run so it isn't left on by accident
+    if config.danger_accept_invalid_certs.unwrap_or(false) {
+        eprintln!("WARNING: TLS certificate verification is disabled (danger_accept_invalid_certs = true).");
+        eprintln!("WARNING: Only use this with a self-hosted instance you trust. Run `bunkr-client config unset danger_accept_invalid_certs` to turn it off.");
+    }
+    let batch_size = cli.batch_size.or(config.default_batch_size).unwrap_or(1);
+    let album_id = cli.album_id.clone().or_else(|| config.default_album_id.clone());
+    let album_name = cli.album_name.clone().or_else(|| config.default_album_name.clone());
 
-    match cli.command {
+    match cli.command.take() {
         Some(Commands::SaveToken { token: save_token }) => {
-            let entry = Entry::new("bunkr_client", "api_token")?;
-            entry.set_password(&save_token)?;
+            let entry = bunkr_client::core::utils::token_entry(cli.profile.as_deref())?;
+            entry.set_password(&bunkr_client::core::utils::normalize_token(&save_token)?)?;
             println!("Token saved securely.");
         }
         Some(Commands::CreateAlbum { name, description, download, public }) => {
-            let token = bunkr_client::core::utils::get_token(cli.token)?;
-            let uploader = BunkrUploader::new(token).await?;
+            let token = bunkr_client::core::utils::get_token(cli.token, cli.profile.as_deref(), config.token_file.as_deref())?;
+            let uploader = BunkrUploader::new(token, &config).await?;
             let id = uploader.create_album(name, description, download, public).await?;
             println!("Album created with ID: {}", id);
         }
-        Some(Commands::Download { album_urls, output_dir }) => {
-            let downloader = bunkr_client::BunkrDownloader::new().await?;
+        Some(Commands::Verify) => {
+            let token = bunkr_client::core::utils::get_token(cli.token, cli.profile.as_deref(), config.token_file.as_deref())?;
+            // Report maintenance mode instead of refusing to start
+            let verify_config = bunkr_client::Config { ignore_maintenance: Some(true), ..config.clone() };
+            let uploader = BunkrUploader::new(token, &verify_config).await?;
+            let username = uploader.account().username.as_deref().unwrap_or("unknown user");
+            println!("Token valid for {}, node {}", username, uploader.upload_url());
+            println!("Maintenance: {}", if uploader.is_maintenance() { "yes, uploads will fail until it ends" } else { "no" });
+            println!("Private: {}", if uploader.is_private() { "yes" } else { "no" });
+        }
+        Some(Commands::Whoami) => {
+            let token = bunkr_client::core::utils::get_token(cli.token, cli.profile.as_deref(), config.token_file.as_deref())?;
+            let uploader = BunkrUploader::new(token, &config).await?;
+            let account = uploader.account();
+            println!("Username: {}", account.username.as_deref().unwrap_or("unknown"));
+            println!("Group:    {}", account.group.as_deref().unwrap_or("unknown"));
+            if let Some(p) = &account.permissions {
+                println!("Permissions:");
+                println!("  user: {}, vip: {}, vvip: {}", p.user, p.vip, p.vvip);
+                println!("  moderator: {}, admin: {}, superadmin: {}", p.moderator, p.admin, p.superadmin);
+            }
+            match &account.retentionPeriods {
+                Some(periods) if !periods.is_empty() => {
+                    let periods: Vec<String> = periods.iter().map(|p| p.to_string()).collect();
+                    println!("Retention periods: {}", periods.join(", "));
+                }
+                _ => println!("Retention periods: none"),
+            }
+            if let Some(default) = account.defaultRetentionPeriod {
+                println!("Default retention period: {}", default);
+            }
+        }
+        Some(Commands::EditAlbum { album_id, description, download, public }) => {
+            if description.is_none() && download.is_none() && public.is_none() {
+                return Err(anyhow::anyhow!("Nothing to change. Pass --description, --download or --public."));
+            }
+            let token = bunkr_client::core::utils::get_token(cli.token, cli.profile.as_deref(), config.token_file.as_deref())?;
+            let uploader = BunkrUploader::new(token, &config).await?;
+            uploader.update_album(album_id, description, download, public).await?;
+            println!("Album {} updated.", album_id);
+        }
+        Some(Commands::MoveFile { file_ids, to, .. }) => {
+            let token = bunkr_client::core::utils::get_token(cli.token, cli.profile.as_deref(), config.token_file.as_deref())?;
+            let uploader = BunkrUploader::new(token, &config).await?;
+            let count = file_ids.len();
+            let plural = if count == 1 { "" } else { "s" };
+            match to {
+                Some(album_id) => {
+                    uploader.move_files(&file_ids, album_id).await?;
+                    println!("Moved {} file{} to album {}.", count, plural, album_id);
+                }
+                None => {
+                    uploader.remove_from_album(&file_ids).await?;
+                    println!("Removed {} file{} from their album.", count, plural);
+                }
+            }
+        }
+        Some(Commands::RenameFile { file_id, new_name }) => {
+            let token = bunkr_client::core::utils::get_token(cli.token, cli.profile.as_deref(), config.token_file.as_deref())?;
+            let uploader = BunkrUploader::new(token, &config).await?;
+            uploader.rename_file(file_id, new_name.clone()).await?;
+            println!("Renamed file {} to {}.", file_id, bunkr_client::core::utils::sanitize_filename(new_name.trim()));
+        }
+        Some(Commands::Download { album_urls, output_dir, template }) => {
+            if let Some(template) = &template {
+                config.set_value("download_template", template)?;
+            }
+            let downloader = new_downloader(&cli, &config).await?;
             let mut files: Vec<_> = Vec::new();
             for url in &album_urls {
                 let mut fetched = downloader.get_files(url).await?;
@@ -172,51 +1293,97 @@ async fn main() -> Result<()> {
             let output_dir = output_dir.unwrap_or_else(|| ".".to_string());
             std::fs::create_dir_all(&output_dir)?;
 
-            let total_bytes: u64 = files.iter().map(|f| f.size as u64).sum();
-
-            #[cfg(feature = "ui")]
-            let ui_state = Some(Arc::new(Mutex::new(UIState::new(files.len(), None, total_bytes))));
-            #[cfg(not(feature = "ui"))]
-            let ui_state: Option<Arc<Mutex<UIState>>> = None;
-            #[cfg(feature = "ui")]
-            let (ui_handle, running) = start_ui(ui_state.as_ref().unwrap().clone());
-
-            let ui_state_for_download = ui_state.as_ref().map(|arc| Arc::clone(arc));
-            downloader.download_files(files, &output_dir, batch_size, ui_state_for_download).await?;
-
-            // Print failed operations
-            #[cfg(feature = "ui")]
-            {
-                stop_ui(ui_handle, running);
-
-                if let Some(ref state) = ui_state {
-                    let state = state.lock().unwrap();
-                    let failed: Vec<_> = state.all_operations.iter()
-                        .filter_map(|(name, status)| {
-                            if let OperationStatus::Failed(info) = status {
-                                Some((name.clone(), info.clone()))
-                            } else {
-                                None
-                            }
-                        })
-                        .collect();
-                    if !failed.is_empty() {
-                        println!("Failed downloads:");
-                        for (name, info) in failed {
-                            println!("  {}: {} (size: {}, status: {:?})", name, info.error, info.file_size, info.status_code);
-                        }
-                    } else {
-                        println!("All downloads completed successfully.");
+            download_with_progress(&cli, &config, &downloader, files, &output_dir, batch_size).await?;
+        }
+        Some(Commands::GetUrl { url }) => {
+            let downloader = new_downloader(&cli, &config).await?;
+            for file in downloader.get_files(&url).await? {
+                println!("{}", downloader.resolve_download_url(&file).await?);
+            }
+        }
+        Some(Commands::Mirror { source_url, album_name }) => {
+            let token = bunkr_client::core::utils::get_token(cli.token.clone(), cli.profile.as_deref(), config.token_file.as_deref())?;
+            let uploader = BunkrUploader::new(token, &config).await?;
+            let album_id = match uploader.get_album_by_name(&album_name).await? {
+                Some(id) => id,
+                None => {
+                    let id = uploader.create_album(album_name.clone(), None, true, true).await?;
+                    println!("Created album '{}' with ID: {}", album_name, id);
+                    id
+                }
+            };
+
+            // Staging copies stay flat, a download template would hide them in subdirectories
+            let downloader = new_downloader(&cli, &bunkr_client::Config { download_template: None, ..config.clone() }).await?;
+            let files = downloader.get_files(&source_url).await?;
+            if files.is_empty() {
+                return Err(anyhow::anyhow!("No files found at {}", source_url));
+            }
+            let names: Vec<PathBuf> = files.iter().enumerate().map(|(i, f)| downloader.file_path(f, i + 1)).collect();
+            let temp_dir = std::env::temp_dir().join(format!("bunkr_mirror_{}", uuid::Uuid::new_v4()));
+            std::fs::create_dir_all(&temp_dir)?;
+
+            let result = async {
+                download_with_progress(&cli, &config, &downloader, files, &temp_dir.to_string_lossy(), batch_size).await?;
+                let (downloaded, download_failed): (Vec<String>, Vec<String>) = names.into_iter()
+                    .map(|name| temp_dir.join(name).to_string_lossy().to_string())
+                    .partition(|path| Path::new(path).is_file());
+                if !download_failed.is_empty() {
+                    println!("Failed to download {} file{}, they won't be uploaded:", download_failed.len(), if download_failed.len() == 1 { "" } else { "s" });
+                    for path in &download_failed {
+                        println!("  {}", Path::new(path).file_name().unwrap_or_default().to_string_lossy());
                     }
                 }
+                if downloaded.is_empty() {
+                    return Err(anyhow::anyhow!("Nothing was downloaded, nothing to upload."));
+                }
+                upload_paths(&cli, &config, downloaded, batch_size, Some(album_id.to_string()), None, FileOverrides::default()).await.map(|_| ())
+            }.await;
+
+            // The downloads are only a staging copy, remove them whether or not the upload went through
+            if let Err(e) = std::fs::remove_dir_all(&temp_dir) {
+                println!("Failed to remove {}: {}", temp_dir.display(), e);
+            }
+            result?;
+        }
+        Some(Commands::RetryFailures { file }) => {
+            let file = file.unwrap_or_else(|| failures_file_path(&cli, &config).with_extension("jsonl"));
+            let (existing, missing): (Vec<String>, Vec<String>) = read_failed_paths(&file)?
+                .into_iter()
+                .partition(|p| Path::new(p).is_file());
+            if !missing.is_empty() {
+                println!("Skipping {} missing file{}:", missing.len(), if missing.len() == 1 { "" } else { "s" });
+                for path in &missing {
+                    println!("  {}", path);
+                }
+            }
+            if existing.is_empty() {
+                return Err(anyhow::anyhow!("No failed files to retry in {}", file.display()));
+            }
+            upload_paths(&cli, &config, existing, batch_size, album_id, album_name, FileOverrides::default()).await?;
+        }
+        Some(Commands::Cache { action }) => {
+            let mut cache = UploadCache::load(cache_file_path(&config))?;
+            match action {
+                CacheAction::Clear => {
+                    let count = cache.len();
+                    cache.clear();
+                    cache.save()?;
+                    println!("Removed {} cached upload{}.", count, if count == 1 { "" } else { "s" });
+                }
+                CacheAction::Prune => {
+                    let removed = cache.prune();
+                    cache.save()?;
+                    println!("Removed {} stale cached upload{}, {} left.", removed, if removed == 1 { "" } else { "s" }, cache.len());
+                }
             }
-            #[cfg(not(feature = "ui"))]
-            println!("Download completed. Check for any errors above.");
         }
         Some(Commands::Config { action }) => {
-            let mut config = bunkr_client::Config::load()?;
+            // Edit the file itself so environment overrides aren't written back
+            let mut config = bunkr_client::Config::load_file(config_path.as_deref())?;
             match action {
                 ConfigAction::Get { key } => {
+                    config.apply_env_overrides()?;
                     if let Some(k) = key {
                         let value = config.get_value(&k);
                         println!("{}", value);
@@ -225,59 +1392,85 @@ async fn main() -> Result<()> {
                     }
                 }
                 ConfigAction::Set { key, value } => {
+                    let previous = config.clone();
                     config.set_value(&key, &value)?;
-                    config.save()?;
-                    println!("Config updated.");
+                    config.save(config_path.as_deref())?;
+                    print_config_changes(&previous, &config);
+                }
+                ConfigAction::Unset { key } => {
+                    let previous = config.clone();
+                    config.unset_value(&key)?;
+                    config.save(config_path.as_deref())?;
+                    print_config_changes(&previous, &config);
+                }
+                ConfigAction::Reset => {
+                    let previous = config.clone();
+                    config = bunkr_client::Config::default();
+                    config.save(config_path.as_deref())?;
+                    print_config_changes(&previous, &config);
+                }
+                ConfigAction::Export { path } => {
+                    std::fs::write(&path, serde_json::to_string_pretty(&config.to_json())?)?;
+                    println!("Config exported to {}", path.display());
+                }
+                ConfigAction::Import { path } => {
+                    let content = std::fs::read_to_string(&path)
+                        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+                    let json: serde_json::Value = serde_json::from_str(&content)
+                        .map_err(|e| anyhow::anyhow!("{} is not valid JSON: {}", path.display(), e))?;
+                    let previous = config.clone();
+                    config.apply_json(&json).map_err(|e| anyhow::anyhow!("{} (in {})", e, path.display()))?;
+                    config.save(config_path.as_deref())?;
+                    print_config_changes(&previous, &config);
                 }
             }
         }
         None => {
-            let all_files = collect_all_files(&cli.paths)?;
-            if all_files.is_empty() {
-                return Err(anyhow::anyhow!("No files to upload."));
+            let include = if cli.include.is_empty() { config.default_include.clone().unwrap_or_default() } else { cli.include.clone() };
+            let exclude = if cli.exclude.is_empty() { config.default_exclude.clone().unwrap_or_default() } else { cli.exclude.clone() };
+            let filter = FileFilter::new(&include, &exclude)?;
+            let (mut paths, display_names) = split_display_names(&cli.paths)?;
+            let mut manifest_albums = vec![];
+            if let Some(file) = &cli.from_file {
+                let manifest = read_manifest(file)?;
+                if !manifest.missing.is_empty() {
+                    println!("Skipping {} missing file{} listed in {}:", manifest.missing.len(), if manifest.missing.len() == 1 { "" } else { "s" }, file.display());
+                    for path in &manifest.missing {
+                        println!("  {}", path);
+                    }
+                }
+                paths.extend(manifest.paths);
+                manifest_albums = manifest.albums;
             }
-
-            let total_bytes: u64 = all_files.iter()
-                .filter_map(|f| std::fs::metadata(f).ok().map(|m| m.len()))
-                .sum();
-
-            let token = bunkr_client::core::utils::get_token(cli.token)?;
-
-            let uploader = BunkrUploader::new(token).await?;
-
-            let album_id = if let Some(name) = album_name {
-                if let Some(id) = uploader.get_album_by_name(&name).await? {
-                    Some(id.to_string())
-                } else {
-                    return Err(anyhow::anyhow!("Album '{}' not found", name));
+            let collected = collect_all_files(&paths, cli.recursive, cli.follow_symlinks, &filter)?;
+            // Walked again per entry to find which of the collected files it covers
+            let mut albums = HashMap::new();
+            for (path, album) in manifest_albums {
+                for file in collect_all_files(std::slice::from_ref(&path), cli.recursive, cli.follow_symlinks, &filter)?.files {
+                    albums.entry(file).or_insert_with(|| album.clone());
                 }
+            }
+            if collected.filtered > 0 {
+                println!("Filtered out {} file{} by include/exclude patterns.", collected.filtered, if collected.filtered == 1 { "" } else { "s" });
+            }
+            let mut duplicates = collected.duplicates;
+            let all_files = if cli.dedupe_content {
+                let (files, content_duplicates) = dedupe_by_content(collected.files)?;
+                duplicates += content_duplicates;
+                files
             } else {
-                album_id
+                collected.files
             };
-
-            #[cfg(feature = "ui")]
-            let ui_state = Some(Arc::new(Mutex::new(UIState::new(all_files.len(), album_id.clone(), total_bytes))));
-            #[cfg(not(feature = "ui"))]
-            let ui_state: Option<Arc<Mutex<UIState>>> = None;
-            #[cfg(feature = "ui")]
-            let (ui_handle, running) = start_ui(ui_state.as_ref().unwrap().clone());
-
-            let (_urls, failures) = uploader.upload_files(all_files, album_id.as_deref(), batch_size, ui_state, Some(&config)).await?;
-
-            #[cfg(feature = "ui")]
-            {
-                stop_ui(ui_handle, running);
+            if duplicates > 0 {
+                println!("Skipped {} duplicate file{}.", duplicates, if duplicates == 1 { "" } else { "s" });
+            }
+            if all_files.is_empty() {
+                return Err(anyhow::anyhow!("No files to upload."));
             }
 
-            // Write the failed uploads to a file
-            if !failures.is_empty() {
-                let mut failed_file = OpenOptions::new()
-                    .create(true)
-                    .append(true)
-                    .open("failed_uploads.txt")?;
-                for failure in &failures {
-                    writeln!(failed_file, "File: {}, Error: {}, Size: {}, Status: {:?}", failure.path, failure.error, failure.file_size, failure.status_code)?;
-                }
+            let run = upload_paths(&cli, &config, all_files, batch_size, album_id, album_name, FileOverrides { display_names, albums }).await?;
+            if config.remember_last.unwrap_or(false) && run.failed == 0 {
+                remember_last_run(&cli, config_path.as_deref(), &run, batch_size)?;
             }
         }
     }
@@ -290,3 +1483,75 @@ fn main() {
     eprintln!("CLI feature not enabled. This binary requires the 'cli' feature.");
     std::process::exit(1);
 }
+
+#[cfg(all(test, feature = "cli"))]
+mod tests {
+    use super::*;
+
+    fn names(collected: &CollectedFiles) -> Vec<String> {
+        let mut names: Vec<String> = collected.files.iter()
+            .map(|f| Path::new(f).file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_cycle_is_walked_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("media");
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        std::fs::write(root.join("a.txt"), "a").unwrap();
+        std::fs::write(root.join("sub/b.txt"), "b").unwrap();
+        std::os::unix::fs::symlink(&root, root.join("sub/loop")).unwrap();
+
+        let filter = FileFilter::new(&[], &[]).unwrap();
+        let collected = collect_all_files(&[root.to_string_lossy().to_string()], true, true, &filter).unwrap();
+        assert_eq!(names(&collected), ["a.txt", "b.txt"]);
+        assert_eq!(collected.duplicates, 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_are_skipped_unless_followed() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("media");
+        let elsewhere = dir.path().join("elsewhere");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::create_dir_all(&elsewhere).unwrap();
+        std::fs::write(root.join("a.txt"), "a").unwrap();
+        std::fs::write(elsewhere.join("c.txt"), "c").unwrap();
+        std::fs::write(elsewhere.join("d.txt"), "d").unwrap();
+        std::os::unix::fs::symlink(elsewhere.join("c.txt"), root.join("c-link.txt")).unwrap();
+        std::os::unix::fs::symlink(&elsewhere, root.join("linked-dir")).unwrap();
+
+        let filter = FileFilter::new(&[], &[]).unwrap();
+        let paths = [root.to_string_lossy().to_string()];
+        assert_eq!(names(&collect_all_files(&paths, true, false, &filter).unwrap()), ["a.txt"]);
+        // c.txt is reached both through its own link and the linked directory
+        let followed = collect_all_files(&paths, true, true, &filter).unwrap();
+        assert_eq!(names(&followed), ["a.txt", "c.txt", "d.txt"]);
+        assert_eq!(followed.duplicates, 1);
+    }
+
+    #[test]
+    fn failed_parts_are_retried_from_their_original() {
+        let dir = tempfile::tempdir().unwrap();
+        let jsonl = dir.path().join("failed_uploads.jsonl");
+        std::fs::write(&jsonl, concat!(
+            r#"{"path":"/tmp/split/part1.mp4","error":"boom","file_size":10,"status_code":500,"source":"/videos/long.mp4"}"#, "\n",
+            r#"{"path":"/tmp/split/part2.mp4","error":"boom","file_size":10,"status_code":500,"source":"/videos/long.mp4"}"#, "\n",
+            r#"{"path":"/photos/a.jpg","error":"boom","file_size":10,"status_code":null}"#, "\n",
+        )).unwrap();
+        assert_eq!(read_failed_paths(&jsonl).unwrap(), ["/videos/long.mp4", "/photos/a.jpg"]);
+
+        let txt = dir.path().join("failed_uploads.txt");
+        std::fs::write(&txt, concat!(
+            "# Run at Thu, 15 Oct 2026 12:00:00 GMT\n",
+            "File: /tmp/split/part1.mp4, Error: boom, Size: 10, Status: Some(500), Source: /videos/long.mp4\n",
+            "File: /photos/a.jpg, Error: boom, Size: 10, Status: None\n",
+        )).unwrap();
+        assert_eq!(read_failed_paths(&txt).unwrap(), ["/videos/long.mp4", "/photos/a.jpg"]);
+    }
+}
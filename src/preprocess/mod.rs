@@ -1 +1,2 @@
+#[allow(clippy::module_inception)]
 pub mod preprocess;
\ No newline at end of file
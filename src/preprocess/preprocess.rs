@@ -1,8 +1,10 @@
 use crate::config::config::Config;
 use anyhow::{Result, anyhow};
+use tracing::warn;
 use mime_guess::from_path;
+use std::io::{BufRead, BufReader};
 use std::path::Path;
-use std::process::Command;
+use std::process::{Command, Stdio};
 use uuid::Uuid;
 
 pub struct PreprocessResult {
@@ -11,15 +13,28 @@ pub struct PreprocessResult {
 }
 
 pub fn preprocess_file(path: &str, max_file_size: u64, config: &Config) -> Result<PreprocessResult> {
+    preprocess_file_with_progress(path, max_file_size, config, &|_| {})
+}
+
+/// Like `preprocess_file`, calling `on_progress` with the fraction done while a video is split.
+pub fn preprocess_file_with_progress(path: &str, max_file_size: u64, config: &Config, on_progress: &dyn Fn(f64)) -> Result<PreprocessResult> {
     let p = Path::new(path);
     let mime = from_path(p).first_or_octet_stream();
 
+    // A custom command replaces the built-in preprocessing
+    if let Some(command) = &config.preprocess_command {
+        return Ok(PreprocessResult {
+            files_to_upload: run_external(command, path)?,
+            preprocess_id: "external".to_string(),
+        });
+    }
+
     // Video preprocessing
     if mime.type_() == mime_guess::mime::VIDEO && config.preprocess_videos.unwrap_or(true) {
         let metadata = p.metadata()?;
         let size = metadata.len();
         if size > max_file_size {
-            let parts = split_video(path, max_file_size)?;
+            let parts = split_video(path, max_file_size, on_progress)?;
             return Ok(PreprocessResult {
                 files_to_upload: parts,
                 preprocess_id: "split_video".to_string(),
@@ -34,6 +49,19 @@ pub fn preprocess_file(path: &str, max_file_size: u64, config: &Config) -> Resul
     })
 }
 
+/// Runs `cleanup_preprocess` when dropped, so temporary files are removed even if the
+/// upload returns early or its task is aborted.
+pub struct PreprocessGuard<'a> {
+    pub result: &'a PreprocessResult,
+    pub original_path: &'a str,
+}
+
+impl Drop for PreprocessGuard<'_> {
+    fn drop(&mut self) {
+        cleanup_preprocess(&self.result.preprocess_id, self.original_path, &self.result.files_to_upload);
+    }
+}
+
 pub fn cleanup_preprocess(preprocess_id: &str, _original_path: &str, files_to_upload: &[String]) {
     match preprocess_id {
         "original" => {
@@ -50,86 +78,312 @@ pub fn cleanup_preprocess(preprocess_id: &str, _original_path: &str, files_to_up
                 }
             }
         }
+        "external" => {
+            // The output directory is ours, including anything the command left besides the uploaded files
+            if let Some(first_file) = files_to_upload.first() {
+                if let Some(parent) = Path::new(first_file).parent() {
+                    let _ = std::fs::remove_dir_all(parent);
+                }
+            }
+        }
         _ => {
             // Unknown preprocess, do nothing
         }
     }
 }
 
-fn split_video(path: &str, max_file_size: u64) -> Result<Vec<String>> {
+/// Runs `command` through the shell with `{input}` and `{outdir}` filled in, returning the files it wrote to `{outdir}`.
+fn run_external(command: &str, path: &str) -> Result<Vec<String>> {
     let p = Path::new(path);
-    let stem = p.file_stem().unwrap().to_string_lossy();
-    let extension = p.extension().unwrap_or_default().to_string_lossy();
+    let parent_dir = p.parent().unwrap_or(Path::new("."));
+    let out_dir = parent_dir.join(format!("bunkr_external_{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&out_dir)?;
+
+    let result = (|| {
+        let command = fill_command(command, path, &out_dir.to_string_lossy());
+        let output = if cfg!(windows) {
+            Command::new("cmd").args(["/C", &command]).output()?
+        } else {
+            Command::new("sh").args(["-c", &command]).output()?
+        };
+        if !output.status.success() {
+            return Err(anyhow!("Preprocess command failed ({}): {}", output.status, String::from_utf8_lossy(&output.stderr).trim()));
+        }
 
+        let mut files = vec![];
+        for entry in std::fs::read_dir(&out_dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                files.push(entry.path().to_string_lossy().to_string());
+            }
+        }
+        if files.is_empty() {
+            return Err(anyhow!("Preprocess command didn't write any files to {}", out_dir.display()));
+        }
+        files.sort();
+        Ok(files)
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_dir_all(&out_dir);
+    }
+    result
+}
+
+/// Quotes `value` as a single shell argument.
+/// Replaces `{input}` and `{outdir}` in `command` with the quoted paths, in one pass so a path
+/// that contains `{outdir}` isn't filled in again inside its own quotes.
+fn fill_command(command: &str, input: &str, out_dir: &str) -> String {
+    let mut filled = String::new();
+    let mut rest = command;
+    while let Some(start) = rest.find('{') {
+        filled.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        if let Some(after) = tail.strip_prefix("{input}") {
+            filled.push_str(&shell_quote(input));
+            rest = after;
+        } else if let Some(after) = tail.strip_prefix("{outdir}") {
+            filled.push_str(&shell_quote(out_dir));
+            rest = after;
+        } else {
+            filled.push('{');
+            rest = &tail[1..];
+        }
+    }
+    filled.push_str(rest);
+    filled
+}
+
+fn shell_quote(value: &str) -> String {
+    if cfg!(windows) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+/// How many times a part that's still too large is split again before falling back to re-encoding.
+const MAX_SPLIT_DEPTH: u32 = 2;
+
+/// Audio bitrate used when re-encoding, subtracted from the size budget of each part.
+const REENCODE_AUDIO_BITRATE: u64 = 128_000;
+
+fn split_video(path: &str, max_file_size: u64, on_progress: &dyn Fn(f64)) -> Result<Vec<String>> {
+    let p = Path::new(path);
     let parent_dir = p.parent().unwrap_or(Path::new("."));
     let temp_dir = parent_dir.join(format!("bunkr_split_{}", Uuid::new_v4()));
     std::fs::create_dir_all(&temp_dir)?;
 
     let hwaccel = detect_hwaccel();
-    let output = Command::new("ffprobe")
-        .args(&[
-            "-v", "quiet",
-            "-show_entries", "format=duration",
-            "-of", "default=noprint_wrappers=1:nokey=1",
-            path
-        ])
-        .output()?;
-    if !output.status.success() {
-        return Err(anyhow!("Failed to get video duration: {}", String::from_utf8_lossy(&output.stderr)));
+    // Progress covers the first pass, which does nearly all the work; re-splitting oversized parts isn't counted
+    let split = segment_video(path, &temp_dir, max_file_size, hwaccel.as_deref(), false, on_progress).and_then(|parts| {
+        let mut result = vec![];
+        for part in parts {
+            result.extend(fit_part(part, &temp_dir, max_file_size, hwaccel.as_deref(), 0)?);
+        }
+        Ok(result)
+    });
+    // No guard owns the parts yet, so clean up here if splitting failed halfway
+    if split.is_err() {
+        let _ = std::fs::remove_dir_all(&temp_dir);
     }
-    let duration_str = String::from_utf8(output.stdout)?;
-    let duration: f64 = duration_str.trim().parse()?;
+    split
+}
+
+/// Splits `part` again until every piece fits, since keyframe spacing makes copied segments uneven.
+/// Re-encodes when copying can't cut the part any further.
+fn fit_part(part: String, temp_dir: &Path, max_file_size: u64, hwaccel: Option<&str>, depth: u32) -> Result<Vec<String>> {
+    if std::fs::metadata(&part)?.len() <= max_file_size {
+        return Ok(vec![part]);
+    }
+
+    if depth < MAX_SPLIT_DEPTH {
+        let pieces = segment_video(&part, temp_dir, max_file_size, hwaccel, false, &|_| {})?;
+        if pieces.len() > 1 {
+            std::fs::remove_file(&part)?;
+            let mut result = vec![];
+            for piece in pieces {
+                result.extend(fit_part(piece, temp_dir, max_file_size, hwaccel, depth + 1)?);
+            }
+            return Ok(result);
+        }
+        // A single piece means there was no keyframe to cut at
+        for piece in pieces {
+            let _ = std::fs::remove_file(piece);
+        }
+    }
+
+    let pieces = segment_video(&part, temp_dir, max_file_size, hwaccel, true, &|_| {})?;
+    std::fs::remove_file(&part)?;
+    for piece in &pieces {
+        let size = std::fs::metadata(piece)?.len();
+        if size > max_file_size {
+            return Err(anyhow!("Re-encoded part {} is still {} bytes, above the max size of {} bytes", piece, size, max_file_size));
+        }
+    }
+    Ok(pieces)
+}
+
+/// Splits `path` into `{stem}_000.{ext}`, `{stem}_001.{ext}`, ... in `out_dir`, returning the parts in order.
+/// With `reencode`, keyframes are forced at each cut and the bitrate is capped so every part fits.
+fn segment_video(path: &str, out_dir: &Path, max_file_size: u64, hwaccel: Option<&str>, reencode: bool, on_progress: &dyn Fn(f64)) -> Result<Vec<String>> {
+    let p = Path::new(path);
+    let stem = p.file_stem().ok_or_else(|| anyhow!("Cannot split {}: path has no file name", path))?.to_string_lossy();
+    let extension = p.extension().unwrap_or_default().to_string_lossy();
 
-    let metadata = p.metadata()?;
-    let size = metadata.len();
-    let parts = (size as f64 / max_file_size as f64).ceil() as u32;
+    let duration = probe_duration(path)?;
+    let size = p.metadata()?.len();
+    let parts = (size as f64 / max_file_size as f64).ceil().max(1.0) as u32;
     let segment_time = duration / parts as f64;
-    let output_pattern = temp_dir.join(format!("{}_%03d.{}", stem, extension)).to_string_lossy().to_string();
+    let output_pattern = out_dir.join(format!("{}_%03d.{}", stem, extension)).to_string_lossy().to_string();
 
     // Build ffmpeg args
     let mut args = vec![];
     if let Some(accel) = hwaccel {
         args.push("-hwaccel".to_string());
-        args.push(accel);
+        args.push(accel.to_string());
     }
     args.push("-loglevel".to_string());
     args.push("quiet".to_string());
+    args.push("-nostats".to_string());
+    args.push("-progress".to_string());
+    args.push("pipe:1".to_string());
     args.push("-i".to_string());
     args.push(path.to_string());
-    args.push("-f".to_string());
-    args.push("segment".to_string());
-    args.push("-segment_time".to_string());
-    args.push(segment_time.to_string());
-    args.push("-c".to_string());
-    args.push("copy".to_string());
+    if reencode {
+        // Leave 5% of each part for container overhead
+        let budget = (max_file_size as f64 * 8.0 * 0.95 / segment_time) as u64;
+        let video_bitrate = budget.checked_sub(REENCODE_AUDIO_BITRATE).filter(|b| *b > 0)
+            .ok_or_else(|| anyhow!("Cannot re-encode {} to fit in {} bytes per part", path, max_file_size))?;
+        args.extend([
+            "-c:v".to_string(), "libx264".to_string(),
+            "-preset".to_string(), "veryfast".to_string(),
+            "-b:v".to_string(), video_bitrate.to_string(),
+            "-maxrate".to_string(), video_bitrate.to_string(),
+            "-bufsize".to_string(), (video_bitrate * 2).to_string(),
+            "-c:a".to_string(), "aac".to_string(),
+            "-b:a".to_string(), REENCODE_AUDIO_BITRATE.to_string(),
+            "-force_key_frames".to_string(), format!("expr:gte(t,n_forced*{})", segment_time),
+        ]);
+        args.push("-f".to_string());
+        args.push("segment".to_string());
+        args.push("-segment_time".to_string());
+        args.push(segment_time.to_string());
+    } else {
+        args.push("-c".to_string());
+        args.push("copy".to_string());
+        args.push("-f".to_string());
+        args.push("segment".to_string());
+        // Cutting at keyframes keeps every part playable on its own
+        match probe_keyframes(path).map(|keyframes| keyframe_cuts(&keyframes, segment_time, parts)) {
+            Ok(cuts) if !cuts.is_empty() => {
+                args.push("-segment_times".to_string());
+                args.push(cuts.iter().map(|t| t.to_string()).collect::<Vec<_>>().join(","));
+            }
+            result => {
+                if let Err(e) = result {
+                    warn!("Falling back to time-based splitting for {}: {}", path, e);
+                }
+                args.push("-segment_time".to_string());
+                args.push(segment_time.to_string());
+            }
+        }
+    }
     args.push("-reset_timestamps".to_string());
     args.push("1".to_string());
     args.push(output_pattern);
 
-    let status = Command::new("ffmpeg")
+    let mut child = Command::new("ffmpeg")
         .args(&args)
-        .status()?;
+        .stdout(Stdio::piped())
+        .spawn()?;
+    if let Some(stdout) = child.stdout.take() {
+        for line in BufReader::new(stdout).lines() {
+            // Reported as key=value lines, out_time_us being how far into the input ffmpeg got
+            if let Some(micros) = line?.strip_prefix("out_time_us=").and_then(|v| v.trim().parse::<f64>().ok()) {
+                if duration > 0.0 {
+                    on_progress((micros / 1_000_000.0 / duration).clamp(0.0, 1.0));
+                }
+            }
+        }
+    }
+    let status = child.wait()?;
     if !status.success() {
         return Err(anyhow!("Failed to split video"));
     }
 
+    // ffmpeg can produce more or fewer segments than asked for, so collect whatever exists
     let mut result = vec![];
-    for i in 0..parts {
-        let part_path = temp_dir.join(format!("{}_{:03}.{}", stem, i, extension)).to_string_lossy().to_string();
-        // Check if file exists and size <= max_file_size
-        if let Ok(meta) = std::fs::metadata(&part_path) {
-            if meta.len() <= max_file_size {
-                result.push(part_path);
-            } else {
-                // If still too big, perhaps further split, but for now, include anyway
-                result.push(part_path);
-            }
+    for i in 0.. {
+        let part_path = out_dir.join(format!("{}_{:03}.{}", stem, i, extension));
+        if !part_path.exists() {
+            break;
         }
+        result.push(part_path.to_string_lossy().to_string());
+    }
+    if result.is_empty() {
+        return Err(anyhow!("Splitting {} produced no parts", path));
     }
 
     Ok(result)
 }
 
+/// Returns the timestamps of the first video stream's keyframes, in seconds.
+fn probe_keyframes(path: &str) -> Result<Vec<f64>> {
+    let output = Command::new("ffprobe")
+        .args([
+            "-v", "quiet",
+            "-select_streams", "v:0",
+            "-skip_frame", "nokey",
+            "-show_frames",
+            "-show_entries", "frame=pts_time",
+            "-of", "csv=p=0",
+            path
+        ])
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow!("Failed to probe keyframes: {}", String::from_utf8_lossy(&output.stderr)));
+    }
+    let keyframes: Vec<f64> = String::from_utf8(output.stdout)?
+        .lines()
+        .filter_map(|line| line.trim().trim_end_matches(',').parse().ok())
+        .collect();
+    if keyframes.is_empty() {
+        return Err(anyhow!("No keyframes found"));
+    }
+    Ok(keyframes)
+}
+
+/// Picks the keyframe closest to each multiple of `segment_time`, skipping the start and repeats.
+fn keyframe_cuts(keyframes: &[f64], segment_time: f64, parts: u32) -> Vec<f64> {
+    let mut cuts: Vec<f64> = vec![];
+    for i in 1..parts {
+        let target = segment_time * i as f64;
+        let closest = keyframes.iter().copied()
+            .filter(|t| *t > cuts.last().copied().unwrap_or(0.0))
+            .min_by(|a, b| (a - target).abs().total_cmp(&(b - target).abs()));
+        if let Some(cut) = closest {
+            cuts.push(cut);
+        }
+    }
+    cuts
+}
+
+fn probe_duration(path: &str) -> Result<f64> {
+    let output = Command::new("ffprobe")
+        .args([
+            "-v", "quiet",
+            "-show_entries", "format=duration",
+            "-of", "default=noprint_wrappers=1:nokey=1",
+            path
+        ])
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow!("Failed to get video duration: {}", String::from_utf8_lossy(&output.stderr)));
+    }
+    let duration_str = String::from_utf8(output.stdout)?;
+    Ok(duration_str.trim().parse()?)
+}
+
 fn detect_hwaccel() -> Option<String> {
     let output = Command::new("ffmpeg").arg("-hwaccels").output();
     match output {
@@ -148,4 +402,80 @@ fn detect_hwaccel() -> Option<String> {
         _ => {}
     }
     None
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn command_placeholders_are_filled_once() {
+        let command = fill_command("cp {input} {outdir}/ {x}", "/media/{outdir} clip.mp4", "/tmp/my out");
+        assert_eq!(command, "cp '/media/{outdir} clip.mp4' '/tmp/my out'/ {x}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn input_named_after_a_placeholder_is_passed_whole() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("with space");
+        std::fs::create_dir(&source).unwrap();
+        let input = source.join("{outdir} clip.txt");
+        std::fs::write(&input, b"clip").unwrap();
+
+        let files = run_external("cp {input} {outdir}/", &input.to_string_lossy()).unwrap();
+        assert_eq!(files.len(), 1);
+        assert!(files[0].ends_with("{outdir} clip.txt"), "{:?}", files);
+        std::fs::remove_dir_all(Path::new(&files[0]).parent().unwrap()).unwrap();
+    }
+
+    fn ffmpeg_available() -> bool {
+        ["ffmpeg", "ffprobe"].iter().all(|tool| Command::new(tool).arg("-version").output().is_ok_and(|o| o.status.success()))
+    }
+
+    #[test]
+    fn cuts_land_on_the_nearest_keyframes() {
+        let keyframes = [0.0, 2.0, 4.1, 5.9, 8.0, 10.0];
+        assert_eq!(keyframe_cuts(&keyframes, 4.0, 3), vec![4.1, 8.0]);
+    }
+
+    #[test]
+    fn cuts_skip_the_start_and_repeats() {
+        // Only one keyframe after the start, so every target picks it and later ones are dropped
+        let keyframes = [0.0, 0.5];
+        assert_eq!(keyframe_cuts(&keyframes, 2.0, 4), vec![0.5]);
+        assert!(keyframe_cuts(&[0.0], 2.0, 3).is_empty());
+    }
+
+    #[test]
+    fn uneven_keyframes_are_split_until_every_part_fits() {
+        if !ffmpeg_available() {
+            eprintln!("ffmpeg not found, skipping");
+            return;
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let video = dir.path().join("uneven.mp4");
+        // Keyframes at 0, 0.5 and 4.5s make a copy split at a third of the size badly uneven
+        let status = Command::new("ffmpeg")
+            .args(["-v", "quiet", "-f", "lavfi", "-i", "testsrc=duration=6:size=320x240:rate=25",
+                "-c:v", "libx264", "-g", "1000", "-force_key_frames", "0,0.5,4.5"])
+            .arg(&video)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let video = video.to_string_lossy().to_string();
+        let max_file_size = std::fs::metadata(&video).unwrap().len() / 3;
+
+        let result = preprocess_file(&video, max_file_size, &Config::default()).unwrap();
+        assert_eq!(result.preprocess_id, "split_video");
+        assert!(result.files_to_upload.len() >= 3);
+        for part in &result.files_to_upload {
+            let size = std::fs::metadata(part).unwrap().len();
+            assert!(size <= max_file_size, "{} is {} bytes, above {}", part, size, max_file_size);
+        }
+
+        cleanup_preprocess(&result.preprocess_id, &video, &result.files_to_upload);
+        assert!(result.files_to_upload.iter().all(|part| !Path::new(part).exists()));
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
@@ -1,9 +1,9 @@
-use std::{collections::HashMap, time::Instant, sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}}};
+use std::{collections::{HashMap, VecDeque}, time::{Duration, Instant}, sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}}};
 use ratatui::{
     backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout},
+    layout::{Constraint, Direction, Layout, Margin},
     style::{Color, Modifier, Style},
-    widgets::{Block, Borders, Paragraph, Table, Row, TableState},
+    widgets::{Block, Borders, Paragraph, Table, Row, TableState, Scrollbar, ScrollbarOrientation, ScrollbarState},
     Terminal,
 };
 use crossterm::{
@@ -13,14 +13,82 @@ use crossterm::{
 };
 use std::io;
 use crate::core::types::FailedOperationInfo;
+use crate::core::progress::ProgressSink;
+use crate::core::utils::format_size;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
 use webbrowser;
+use arboard::Clipboard;
 
 #[derive(Clone)]
 pub enum OperationStatus {
-    Preprocessing,
+    /// Waiting for a free upload slot. Its upload age can still be changed.
+    Pending,
+    /// Preprocessing, with its progress if the step reports any.
+    Preprocessing(f64),
     Ongoing(f64),
     Completed,
     Failed(FailedOperationInfo),
+    /// Not uploaded, with the reason.
+    Skipped(String),
+}
+
+impl OperationStatus {
+    /// Color the row is drawn in.
+    fn color(&self) -> Color {
+        match self {
+            OperationStatus::Failed(_) => Color::Red,
+            OperationStatus::Completed => Color::Green,
+            OperationStatus::Preprocessing(_) => Color::Magenta,
+            OperationStatus::Pending | OperationStatus::Ongoing(_) => Color::Yellow,
+            OperationStatus::Skipped(_) => Color::DarkGray,
+        }
+    }
+
+    /// Section the row is listed under when the table is grouped by status, failed first.
+    fn group(&self) -> (usize, &'static str) {
+        match self {
+            OperationStatus::Failed(_) => (0, "Failed"),
+            OperationStatus::Pending | OperationStatus::Preprocessing(_) | OperationStatus::Ongoing(_) => (1, "Ongoing"),
+            OperationStatus::Completed | OperationStatus::Skipped(_) => (2, "Completed"),
+        }
+    }
+}
+
+/// A line of the operations table.
+pub enum TableRow<'a> {
+    /// Section header with the number of operations under it.
+    Group(&'static str, usize),
+    Operation(&'a String, &'a OperationStatus),
+}
+
+/// Totals of a finished run, printed once the TUI has cleared the screen.
+#[derive(Clone, Debug)]
+pub struct UploadSummary {
+    pub completed: usize,
+    pub failed: usize,
+    pub total_files: usize,
+    /// Bytes actually sent, not counting skipped files.
+    pub transferred_bytes: u64,
+    pub elapsed: Duration,
+    /// Fastest combined speed over any few seconds, in bytes per second.
+    pub peak_speed: f64,
+}
+
+impl std::fmt::Display for UploadSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let seconds = self.elapsed.as_secs_f64();
+        let average = if seconds > 0.0 { self.transferred_bytes as f64 / seconds } else { 0.0 };
+        write!(
+            f,
+            "Uploaded {}/{} files ({}) in {:.1}s, {}/s on average, {}/s peak.",
+            self.completed, self.total_files, format_size(self.transferred_bytes), seconds,
+            format_size(average as u64), format_size(self.peak_speed as u64),
+        )?;
+        if self.failed > 0 {
+            write!(f, " {} failed.", self.failed)?;
+        }
+        Ok(())
+    }
 }
 
 pub struct UIState {
@@ -33,34 +101,205 @@ pub struct UIState {
     pub album_id: Option<String>,
     pub file_sizes: HashMap<String, u64>,
     pub completed_urls: HashMap<String, String>,
+    retry_tx: UnboundedSender<String>,
+    retry_rx: Option<UnboundedReceiver<String>>,
+    status_message: Option<(String, Instant)>,
+    /// Case-insensitive filename filter applied to the operations table.
+    pub filter_query: String,
+    /// Whether typed characters currently go into `filter_query`.
+    pub filter_input: bool,
+    /// Whether the table lists failed, ongoing and completed operations in separate sections, toggled with `g`.
+    pub group_by_status: bool,
+    /// Recent (time, bytes transferred so far) samples per file, used for per-row speed.
+    transfer_samples: HashMap<String, VecDeque<(Instant, u64)>>,
+    /// Upload ages the account may choose from, cycled through with `a` on a pending row.
+    pub retention_periods: Vec<i64>,
+    /// Upload age picked for a file, overriding the configured default.
+    file_ages: HashMap<String, i64>,
+    /// Bytes sent so far, unlike `processed_bytes` without skipped files and including resent ones.
+    transferred_bytes: u64,
+    /// Recent (time, transferred_bytes) samples for the combined speed.
+    speed_samples: VecDeque<(Instant, u64)>,
+    peak_speed: f64,
+    /// Bumped by every progress notification so the TUI only redraws after a change.
+    revision: u64,
 }
 
 impl UIState {
     pub fn new(total_files: usize, album_id: Option<String>, total_bytes: u64) -> Self {
+        let (retry_tx, retry_rx) = unbounded_channel();
+        let start_time = Instant::now();
         Self {
             total_files,
             processed_files: 0,
             processed_bytes: 0,
             total_bytes,
-            start_time: Instant::now(),
+            start_time,
             all_operations: HashMap::new(),
             album_id,
             file_sizes: HashMap::new(),
             completed_urls: HashMap::new(),
+            retry_tx,
+            retry_rx: Some(retry_rx),
+            status_message: None,
+            filter_query: String::new(),
+            filter_input: false,
+            group_by_status: false,
+            transfer_samples: HashMap::new(),
+            retention_periods: Vec::new(),
+            file_ages: HashMap::new(),
+            transferred_bytes: 0,
+            speed_samples: VecDeque::from([(start_time, 0)]),
+            peak_speed: 0.0,
+            revision: 0,
         }
     }
 
+    /// Shows a short message in the header for a few seconds.
+    pub fn set_status_message(&mut self, message: String) {
+        self.status_message = Some((message, Instant::now()));
+    }
+
+    fn current_status_message(&self) -> Option<&str> {
+        self.status_message.as_ref()
+            .filter(|(_, at)| at.elapsed() < STATUS_MESSAGE_DURATION)
+            .map(|(message, _)| message.as_str())
+    }
+
+    pub fn completed_urls_sorted(&self) -> Vec<&String> {
+        let mut urls: Vec<(&String, &String)> = self.completed_urls.iter().collect();
+        urls.sort_by(|a, b| a.0.cmp(b.0));
+        urls.into_iter().map(|(_, url)| url).collect()
+    }
+
+    pub fn sorted_operations(&self) -> Vec<(&String, &OperationStatus)> {
+        let mut operations: Vec<(&String, &OperationStatus)> = self.all_operations.iter().collect();
+        operations.sort_by(|a, b| a.0.cmp(b.0));
+        operations
+    }
+
+    /// Sorted operations narrowed down to the rows matching `filter_query`.
+    pub fn visible_operations(&self) -> Vec<(&String, &OperationStatus)> {
+        let query = self.filter_query.to_lowercase();
+        self.sorted_operations().into_iter()
+            .filter(|(name, _)| query.is_empty() || display_name(name).to_lowercase().contains(&query))
+            .collect()
+    }
+
+    /// Rows of the operations table: the visible operations, under a header per status when grouped.
+    pub fn table_rows(&self) -> Vec<TableRow<'_>> {
+        let mut operations = self.visible_operations();
+        if !self.group_by_status {
+            return operations.into_iter().map(|(name, status)| TableRow::Operation(name, status)).collect();
+        }
+        // Stable, so rows stay sorted by name within a group
+        operations.sort_by_key(|(_, status)| status.group().0);
+        let mut rows = Vec::new();
+        for (i, (name, status)) in operations.iter().enumerate() {
+            let (rank, label) = status.group();
+            if i == 0 || operations[i - 1].1.group().0 != rank {
+                let count = operations[i..].iter().take_while(|(_, s)| s.group().0 == rank).count();
+                rows.push(TableRow::Group(label, count));
+            }
+            rows.push(TableRow::Operation(name, status));
+        }
+        rows
+    }
+
+    /// The operation shown at table row `row`, `None` for a group header.
+    pub fn operation_at(&self, row: usize) -> Option<(&String, &OperationStatus)> {
+        match self.table_rows().into_iter().nth(row)? {
+            TableRow::Operation(name, status) => Some((name, status)),
+            TableRow::Group(..) => None,
+        }
+    }
+
+    fn row_of(&self, name: &str) -> Option<usize> {
+        self.table_rows().iter().position(|row| matches!(row, TableRow::Operation(n, _) if n.as_str() == name))
+    }
+
+    pub fn clear_filter(&mut self) {
+        self.filter_query.clear();
+        self.filter_input = false;
+    }
+
+    /// Re-queues a failed operation. Does nothing unless it failed and an uploader is draining retries.
+    pub fn request_retry(&mut self, name: &str) -> bool {
+        if self.retry_rx.is_some() || !matches!(self.all_operations.get(name), Some(OperationStatus::Failed(_))) {
+            return false;
+        }
+        if self.retry_tx.send(name.to_string()).is_err() {
+            return false;
+        }
+        self.all_operations.insert(name.to_string(), OperationStatus::Ongoing(0.0));
+        true
+    }
+
+    pub fn retry_all_failed(&mut self) -> usize {
+        let failed: Vec<String> = self.all_operations.iter()
+            .filter(|(_, status)| matches!(status, OperationStatus::Failed(_)))
+            .map(|(name, _)| name.clone())
+            .collect();
+        failed.iter().filter(|name| self.request_retry(name)).count()
+    }
+
+    /// Marks `name` as uploading. A file that starts over, e.g. after a failed attempt, gives back
+    /// the bytes it counted so far, so resent bytes aren't counted twice toward progress and ETA.
     pub fn add_current_operation(&mut self, name: String, progress: f64, size: u64) {
+        self.processed_bytes = self.processed_bytes.saturating_sub(self.file_transferred(&name));
         self.all_operations.insert(name.clone(), OperationStatus::Ongoing(progress));
+        self.transfer_samples.insert(name.clone(), VecDeque::from([(Instant::now(), 0)]));
         self.file_sizes.insert(name, size);
     }
 
+    pub fn add_file_bytes(&mut self, name: &str, bytes: u64) {
+        let samples = self.transfer_samples.entry(name.to_string()).or_default();
+        let now = Instant::now();
+        let transferred = samples.back().map(|(_, b)| *b).unwrap_or(0) + bytes;
+        samples.push_back((now, transferred));
+        // Keep one sample older than the window as the baseline, drop anything before it
+        while samples.len() > 2 && samples.get(1).is_some_and(|(t, _)| now.duration_since(*t) > SPEED_WINDOW) {
+            samples.pop_front();
+        }
+    }
+
+    /// Bytes per second for a file over the last few seconds, 0 when nothing moved recently.
+    pub fn file_speed(&self, name: &str) -> f64 {
+        let Some(samples) = self.transfer_samples.get(name) else {
+            return 0.0;
+        };
+        let Some(&(_, latest)) = samples.back() else {
+            return 0.0;
+        };
+        let now = Instant::now();
+        let baseline = samples.iter().rev()
+            .find(|(t, _)| now.duration_since(*t) >= SPEED_WINDOW)
+            .or(samples.front());
+        match baseline {
+            Some(&(t, bytes)) => {
+                let elapsed = now.duration_since(t).as_secs_f64();
+                if elapsed > 0.0 { latest.saturating_sub(bytes) as f64 / elapsed } else { 0.0 }
+            }
+            None => 0.0,
+        }
+    }
+
+    pub fn file_transferred(&self, name: &str) -> u64 {
+        self.transfer_samples.get(name).and_then(|s| s.back()).map(|(_, b)| *b).unwrap_or(0)
+    }
+
     pub fn update_progress(&mut self, name: &str, progress: f64) {
         if let Some(OperationStatus::Ongoing(ref mut p)) = self.all_operations.get_mut(name) {
             *p = progress;
         }
     }
 
+    pub fn update_preprocess_progress(&mut self, name: &str, progress: f64) {
+        if let Some(OperationStatus::Preprocessing(ref mut p)) = self.all_operations.get_mut(name) {
+            *p = progress;
+        }
+    }
+
     pub fn remove_current_operation(&mut self, name: &str, url: Option<&str>) {
         self.all_operations.insert(name.to_string(), OperationStatus::Completed);
         self.processed_files += 1;
@@ -71,15 +310,88 @@ impl UIState {
 
     pub fn add_processed_bytes(&mut self, bytes: u64) {
         self.processed_bytes += bytes;
+        self.transferred_bytes += bytes;
+        let now = Instant::now();
+        self.speed_samples.push_back((now, self.transferred_bytes));
+        while self.speed_samples.len() > 2 && self.speed_samples.get(1).is_some_and(|(t, _)| now.duration_since(*t) > SPEED_WINDOW) {
+            self.speed_samples.pop_front();
+        }
+        if let Some(&(t, bytes)) = self.speed_samples.front() {
+            let elapsed = now.duration_since(t).as_secs_f64();
+            // Shorter spans would turn a single chunk into a spike
+            if elapsed >= 1.0 {
+                self.peak_speed = self.peak_speed.max(self.transferred_bytes.saturating_sub(bytes) as f64 / elapsed);
+            }
+        }
+    }
+
+    /// Totals so far, for printing after the TUI is gone.
+    pub fn summary(&self) -> UploadSummary {
+        let count = |matches: fn(&OperationStatus) -> bool| self.all_operations.values().filter(|s| matches(s)).count();
+        let elapsed = self.start_time.elapsed();
+        // Runs shorter than the sampling span never record a peak
+        let average = self.transferred_bytes as f64 / elapsed.as_secs_f64().max(f64::EPSILON);
+        UploadSummary {
+            completed: count(|s| matches!(s, OperationStatus::Completed)),
+            failed: count(|s| matches!(s, OperationStatus::Failed(_))),
+            total_files: self.total_files,
+            transferred_bytes: self.transferred_bytes,
+            elapsed,
+            peak_speed: self.peak_speed.max(average),
+        }
     }
 
     pub fn add_failed_operation(&mut self, name: String, info: FailedOperationInfo) {
         self.all_operations.insert(name, OperationStatus::Failed(info));
     }
 
+    /// Records a file that won't be uploaded. Counts as processed so overall progress still reaches 100%.
+    pub fn add_skipped(&mut self, name: String, size: u64, reason: String) {
+        self.file_sizes.insert(name.clone(), size);
+        self.all_operations.insert(name, OperationStatus::Skipped(reason));
+        self.processed_files += 1;
+        self.processed_bytes += size;
+    }
+
+    /// Records a file whose earlier upload was found in the upload cache.
+    pub fn add_cached(&mut self, name: String, size: u64, url: String) {
+        self.file_sizes.insert(name.clone(), size);
+        self.all_operations.insert(name.clone(), OperationStatus::Completed);
+        self.completed_urls.insert(name, url);
+        self.processed_files += 1;
+        self.processed_bytes += size;
+    }
+
+    pub fn add_pending(&mut self, name: String, size: u64) {
+        self.file_sizes.insert(name.clone(), size);
+        self.all_operations.insert(name, OperationStatus::Pending);
+    }
+
+    /// Moves a pending file to the next retention period, wrapping around to the default.
+    /// Returns the new age, or `None` if the file already started or there's nothing to pick from.
+    pub fn cycle_upload_age(&mut self, name: &str) -> Option<Option<i64>> {
+        if self.retention_periods.is_empty() || !matches!(self.all_operations.get(name), Some(OperationStatus::Pending)) {
+            return None;
+        }
+        let next = match self.file_ages.get(name) {
+            None => self.retention_periods.first().copied(),
+            Some(age) => self.retention_periods.iter().position(|p| p == age)
+                .and_then(|i| self.retention_periods.get(i + 1).copied()),
+        };
+        match next {
+            Some(age) => self.file_ages.insert(name.to_string(), age),
+            None => self.file_ages.remove(name),
+        };
+        Some(next)
+    }
+
+    pub fn upload_age(&self, name: &str) -> Option<i64> {
+        self.file_ages.get(name).copied()
+    }
+
     pub fn add_preprocessing(&mut self, name: String, size: u64) {
         self.file_sizes.insert(name.clone(), size);
-        self.all_operations.insert(name, OperationStatus::Preprocessing);
+        self.all_operations.insert(name, OperationStatus::Preprocessing(0.0));
     }
 
     pub fn remove_operation(&mut self, name: &str) {
@@ -92,15 +404,86 @@ impl UIState {
     }
 }
 
-fn format_size(size: u64) -> String {
-    if size >= 1024 * 1024 * 1024 {
-        format!("{:.1} GB", size as f64 / (1024.0 * 1024.0 * 1024.0))
-    } else if size >= 1024 * 1024 {
-        format!("{:.1} MB", size as f64 / (1024.0 * 1024.0))
-    } else if size >= 1024 {
-        format!("{:.1} KB", size as f64 / 1024.0)
+/// Locks the state for a change the TUI has to redraw for.
+fn changed(state: &Mutex<UIState>) -> std::sync::MutexGuard<'_, UIState> {
+    let mut state = state.lock().unwrap();
+    state.revision += 1;
+    state
+}
+
+impl ProgressSink for Mutex<UIState> {
+    fn on_start(&self, path: &str, size: u64) {
+        changed(self).add_current_operation(path.to_string(), 0.0, size);
+    }
+
+    fn on_progress(&self, path: &str, progress: f64) {
+        changed(self).update_progress(path, progress);
+    }
+
+    fn on_complete(&self, path: &str, url: Option<&str>) {
+        changed(self).remove_current_operation(path, url);
+    }
+
+    fn on_fail(&self, path: &str, info: &FailedOperationInfo) {
+        changed(self).add_failed_operation(path.to_string(), info.clone());
+    }
+
+    fn on_bytes(&self, path: &str, bytes: u64) {
+        let mut state = changed(self);
+        state.add_processed_bytes(bytes);
+        state.add_file_bytes(path, bytes);
+    }
+
+    fn on_skip(&self, path: &str, reason: &str) {
+        changed(self).add_skipped(path.to_string(), 0, reason.to_string());
+    }
+
+    fn on_preprocess(&self, path: &str, size: u64) {
+        changed(self).add_preprocessing(path.to_string(), size);
+    }
+
+    fn upload_age(&self, path: &str) -> Option<i64> {
+        self.lock().unwrap().upload_age(path)
+    }
+
+    fn on_preprocess_progress(&self, path: &str, progress: f64) {
+        changed(self).update_preprocess_progress(path, progress);
+    }
+
+    fn take_retry_receiver(&self) -> Option<UnboundedReceiver<String>> {
+        self.lock().unwrap().retry_rx.take()
+    }
+
+    fn on_preprocessed(&self, path: &str, files: &[String]) {
+        let mut state = changed(self);
+        if files.len() > 1 {
+            state.add_to_total_files(files.len() - 1);
+        }
+        if files.len() == 1 && files[0] == path {
+            state.all_operations.insert(path.to_string(), OperationStatus::Ongoing(0.0));
+        } else {
+            // The original is replaced by its preprocessed parts, which get their own rows
+            state.remove_operation(path);
+        }
+    }
+}
+
+fn display_name(name: &str) -> std::borrow::Cow<'_, str> {
+    std::path::Path::new(name).file_name().unwrap_or(std::ffi::OsStr::new(name)).to_string_lossy()
+}
+
+const STATUS_MESSAGE_DURATION: Duration = Duration::from_secs(3);
+/// Redraw at least this often without changes, so speeds, ETAs and status messages stay current.
+const IDLE_REDRAW_INTERVAL: Duration = Duration::from_secs(1);
+const SPEED_WINDOW: Duration = Duration::from_secs(5);
+
+fn format_eta(seconds: f64) -> String {
+    if seconds < 60.0 {
+        format!("{:.0}s", seconds)
+    } else if seconds < 3600.0 {
+        format!("{:.0}m", seconds / 60.0)
     } else {
-        format!("{} B", size)
+        format!("{:.1}h", seconds / 3600.0)
     }
 }
 
@@ -108,6 +491,12 @@ pub struct UI {
     terminal: Terminal<CrosstermBackend<io::Stdout>>,
     table_state: TableState,
     previous_row_count: usize,
+    /// Number of table rows that fit on screen, the distance PageUp/PageDown jump.
+    page_height: usize,
+    /// False when `NO_COLOR` is set, see <https://no-color.org>.
+    colors: bool,
+    // Kept alive for the whole session, some platforms drop the contents with the handle
+    clipboard: Option<Clipboard>,
 }
 
 impl UI {
@@ -117,10 +506,20 @@ impl UI {
         execute!(stdout, EnterAlternateScreen)?;
         let backend = CrosstermBackend::new(stdout);
         let terminal = Terminal::new(backend)?;
-        Ok(Self { terminal, table_state: TableState::default(), previous_row_count: 0 })
+        let colors = std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty());
+        Ok(Self { terminal, table_state: TableState::default(), previous_row_count: 0, page_height: 1, colors, clipboard: None })
+    }
+
+    fn copy_to_clipboard(&mut self, text: String) -> Result<(), arboard::Error> {
+        if self.clipboard.is_none() {
+            self.clipboard = Some(Clipboard::new()?);
+        }
+        self.clipboard.as_mut().unwrap().set_text(text)
     }
 
     pub fn draw(&mut self, state: &UIState) -> Result<(), Box<dyn std::error::Error>> {
+        let colors = self.colors;
+        let fg = |color: Color| if colors { Style::default().fg(color) } else { Style::default() };
         self.terminal.draw(|f| {
             let size = f.area();
             let elapsed = state.start_time.elapsed().as_secs_f64();
@@ -130,26 +529,22 @@ impl UI {
             let remaining_bytes: u64 = state.total_bytes.saturating_sub(state.processed_bytes);
 
             let eta_str = if remaining_bytes > 0 && bytes_per_sec > 0.0 {
-                let time_left_seconds = remaining_bytes as f64 / bytes_per_sec;
-                if time_left_seconds < 60.0 {
-                    format!(" | ETA: {:.0}s", time_left_seconds)
-                } else if time_left_seconds < 3600.0 {
-                    format!(" | ETA: {:.0}m", time_left_seconds / 60.0)
-                } else {
-                    format!(" | ETA: {:.1}h", time_left_seconds / 3600.0)
-                }
+                format!(" | ETA: {}", format_eta(remaining_bytes as f64 / bytes_per_sec))
             } else {
                 String::new()
             };
 
-            let header_text = if let Some(album) = &state.album_id {
+            let mut header_text = if let Some(album) = &state.album_id {
                 format!("Bunkr Client | Album: {} | Processed: {}/{} | Speed: {:.2} MB/s{}", album, state.processed_files, state.total_files, speed_mb_s, eta_str)
             } else {
                 format!("Bunkr Client | Processed: {}/{} | Speed: {:.2} MB/s{}", state.processed_files, state.total_files, speed_mb_s, eta_str)
             };
+            if let Some(message) = state.current_status_message() {
+                header_text.push_str(&format!(" | {}", message));
+            }
             let header = Paragraph::new(header_text)
                 .block(Block::default().borders(Borders::ALL).title("Header"))
-                .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD));
+                .style(fg(Color::Cyan).add_modifier(Modifier::BOLD));
 
             let header_height = 3;
             let chunks = Layout::default()
@@ -160,21 +555,33 @@ impl UI {
             f.render_widget(header, chunks[0]);
 
             let list_area = chunks[1];
+            // Minus the borders and the column header
+            self.page_height = (list_area.height as usize).saturating_sub(3).max(1);
 
-            let mut all_items_vec: Vec<(&String, &OperationStatus)> = state.all_operations.iter().collect();
-            all_items_vec.sort_by(|a, b| a.0.cmp(b.0));
+            let table_rows = state.table_rows();
 
-            let current_row_count = all_items_vec.len();
+            let current_row_count = table_rows.len();
+            let operation_count = table_rows.iter().filter(|row| matches!(row, TableRow::Operation(..))).count();
 
-            let rows: Vec<Row> = all_items_vec.iter().map(|(name, status)| {
-                let file_name = std::path::Path::new(name).file_name().unwrap_or(std::ffi::OsStr::new(name)).to_string_lossy();
+            let rows: Vec<Row> = table_rows.iter().map(|row| {
+                let (name, status) = match row {
+                    TableRow::Group(label, count) => {
+                        return Row::new(vec![format!("{} ({})", label, count)])
+                            .style(fg(Color::Yellow).add_modifier(Modifier::BOLD));
+                    }
+                    TableRow::Operation(name, status) => (name, *status),
+                };
+                let file_name = display_name(name);
                 let size = match status {
                     OperationStatus::Failed(info) => info.file_size,
                     _ => *state.file_sizes.get(*name).unwrap_or(&0),
                 };
                 let size_str = format_size(size);
                 let (progress_str, status_str, url_str) = match status {
-                    OperationStatus::Preprocessing => ("".to_string(), "Preprocessing".to_string(), "".to_string()),
+                    OperationStatus::Pending => ("".to_string(), "Pending".to_string(), "".to_string()),
+                    // Video splitting is the only step that reports progress
+                    OperationStatus::Preprocessing(progress) if *progress > 0.0 => (format!("{:.0}%", progress * 100.0), format!("Splitting {:.0}%", progress * 100.0), "".to_string()),
+                    OperationStatus::Preprocessing(_) => ("".to_string(), "Preprocessing".to_string(), "".to_string()),
                     OperationStatus::Ongoing(progress) => (format!("{:.0}%", progress * 100.0), "Ongoing".to_string(), "".to_string()),
                     OperationStatus::Completed => {
                         let url = state.completed_urls.get(*name).cloned().unwrap_or_else(|| "".to_string());
@@ -188,23 +595,44 @@ impl UI {
                         };
                         ("".to_string(), format!("Failed{}: {}", status_str_inner, info.error), "".to_string())
                     }
+                    OperationStatus::Skipped(reason) => ("".to_string(), format!("Skipped: {}", reason), "".to_string()),
+                };
+                let (speed_str, row_eta_str) = if let OperationStatus::Ongoing(_) = status {
+                    let speed = state.file_speed(name);
+                    let remaining = size.saturating_sub(state.file_transferred(name));
+                    let eta = if speed > 0.0 && remaining > 0 { format_eta(remaining as f64 / speed) } else { "-".to_string() };
+                    (format!("{}/s", format_size(speed as u64)), eta)
+                } else {
+                    ("".to_string(), "".to_string())
                 };
-                Row::new(vec![file_name.to_string(), size_str, progress_str, status_str, url_str])
+                let age_str = state.upload_age(name).map(|age| age.to_string()).unwrap_or_default();
+                Row::new(vec![file_name.to_string(), size_str, progress_str, speed_str, row_eta_str, age_str, status_str, url_str])
+                    .style(fg(status.color()))
             }).collect();
 
             let widths = [
-                Constraint::Percentage(25),
-                Constraint::Percentage(12),
-                Constraint::Percentage(12),
-                Constraint::Percentage(25),
-                Constraint::Percentage(26),
+                Constraint::Percentage(20),
+                Constraint::Percentage(8),
+                Constraint::Percentage(8),
+                Constraint::Percentage(10),
+                Constraint::Percentage(7),
+                Constraint::Percentage(6),
+                Constraint::Percentage(20),
+                Constraint::Percentage(21),
             ];
 
+            let table_title = if state.filter_input || !state.filter_query.is_empty() {
+                let cursor = if state.filter_input { "_" } else { "" };
+                format!("Operations | Filter: {}{} ({} of {})", state.filter_query, cursor, operation_count, state.all_operations.len())
+            } else {
+                "Operations".to_string()
+            };
+
             let table = Table::new(rows, widths)
-                .block(Block::default().borders(Borders::ALL).title("Operations"))
+                .block(Block::default().borders(Borders::ALL).title(table_title))
                 .header(
-                    Row::new(vec!["File", "Size", "Progress", "Status", "URL"])
-                        .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
+                    Row::new(vec!["File", "Size", "Progress", "Speed", "ETA", "Age", "Status", "URL"])
+                        .style(fg(Color::Cyan).add_modifier(Modifier::BOLD))
                 )
                 .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
 
@@ -216,11 +644,42 @@ impl UI {
 
             self.previous_row_count = current_row_count;
 
+            // Keep the selection inside the (possibly filtered) rows
+            if let Some(selected) = self.table_state.selected() {
+                if current_row_count == 0 {
+                    self.table_state.select(None);
+                } else if selected >= current_row_count {
+                    self.table_state.select(Some(current_row_count - 1));
+                }
+            }
+
             f.render_stateful_widget(table, list_area, &mut self.table_state);
+
+            let mut scrollbar_state = ScrollbarState::new(current_row_count)
+                .viewport_content_length(self.page_height)
+                .position(self.table_state.selected().unwrap_or_else(|| self.table_state.offset()));
+            f.render_stateful_widget(
+                Scrollbar::new(ScrollbarOrientation::VerticalRight),
+                list_area.inner(Margin { vertical: 1, horizontal: 0 }),
+                &mut scrollbar_state,
+            );
         })?;
         Ok(())
     }
 
+    /// Moves the selection off a group header onto the nearest operation, in the direction it was moving.
+    fn skip_group_header(&mut self, state: &UIState, forward: bool) {
+        let rows = state.table_rows();
+        let Some(selected) = self.table_state.selected().map(|selected| selected.min(rows.len().saturating_sub(1))) else {
+            return;
+        };
+        if let Some(TableRow::Group(..)) = rows.get(selected) {
+            // Groups are never empty, so an operation always follows a header
+            let selected = if forward || selected == 0 { selected + 1 } else { selected - 1 };
+            self.table_state.select(Some(selected));
+        }
+    }
+
     pub fn restore(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         disable_raw_mode()?;
         execute!(self.terminal.backend_mut(), LeaveAlternateScreen)?;
@@ -229,56 +688,160 @@ impl UI {
     }
 }
 
-pub fn start_ui(ui_state: Arc<Mutex<UIState>>) -> (std::thread::JoinHandle<()>, Arc<AtomicBool>) {
+/// Runs the TUI on its own thread. Input is checked every `refresh_interval`, and the screen is
+/// redrawn at most that often, only after a key press or a change to `ui_state`.
+pub fn start_ui(ui_state: Arc<Mutex<UIState>>, refresh_interval: Duration) -> (std::thread::JoinHandle<()>, Arc<AtomicBool>) {
     let running = Arc::new(AtomicBool::new(true));
     let running_clone = running.clone();
     let ui_state_clone = ui_state.clone();
     let handle = std::thread::spawn(move || {
         let mut ui = UI::new().unwrap();
+        let mut drawn_revision = None;
+        let mut last_draw = Instant::now();
         while running_clone.load(Ordering::Relaxed) {
-            if event::poll(std::time::Duration::from_millis(0)).unwrap_or(false) {
+            let mut key_pressed = false;
+            if event::poll(refresh_interval).unwrap_or(false) {
+                key_pressed = true;
                 if let Ok(Event::Key(key_event)) = event::read() {
                     if key_event.kind == KeyEventKind::Press || key_event.kind == KeyEventKind::Repeat {
-                        match key_event.code {
-                            KeyCode::Char('c') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
-                                running_clone.store(false, Ordering::Relaxed);
-                                break;
-                            }
-                            KeyCode::Up => {
-                                let selected = ui.table_state.selected().unwrap_or(0);
-                                if selected > 0 {
-                                    ui.table_state.select(Some(selected - 1));
+                        let filter_input = ui_state_clone.lock().unwrap().filter_input;
+                        if filter_input && !key_event.modifiers.contains(KeyModifiers::CONTROL) {
+                            let mut state = ui_state_clone.lock().unwrap();
+                            match key_event.code {
+                                KeyCode::Char(c) => state.filter_query.push(c),
+                                KeyCode::Backspace => {
+                                    state.filter_query.pop();
                                 }
+                                KeyCode::Enter => state.filter_input = false,
+                                KeyCode::Esc => state.clear_filter(),
+                                _ => {}
                             }
-                            KeyCode::Down => {
-                                let selected = ui.table_state.selected().unwrap_or(0);
-                                ui.table_state.select(Some(selected + 1));
-                            }
-                            KeyCode::Enter => {
-                                let state = ui_state_clone.lock().unwrap();
-                                let mut all_items_vec: Vec<(&String, &OperationStatus)> = state.all_operations.iter().collect();
-                                all_items_vec.sort_by(|a, b| a.0.cmp(b.0));
-                                if let Some(selected) = ui.table_state.selected() {
-                                    if selected < all_items_vec.len() {
-                                        let (name, status) = &all_items_vec[selected];
-                                        if let OperationStatus::Completed = status {
-                                            if let Some(url) = state.completed_urls.get(*name) {
-                                                let _ = webbrowser::open(url);
-                                            }
+                            // Falls through to the draw below, so the typed character shows right away
+                        } else {
+                            match key_event.code {
+                                KeyCode::Char('c') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                                    running_clone.store(false, Ordering::Relaxed);
+                                    break;
+                                }
+                                KeyCode::Char('/') => {
+                                    ui_state_clone.lock().unwrap().filter_input = true;
+                                }
+                                KeyCode::Esc => {
+                                    ui_state_clone.lock().unwrap().clear_filter();
+                                }
+                                KeyCode::Up => {
+                                    let selected = ui.table_state.selected().unwrap_or(0);
+                                    if selected > 0 {
+                                        ui.table_state.select(Some(selected - 1));
+                                    }
+                                    ui.skip_group_header(&ui_state_clone.lock().unwrap(), false);
+                                }
+                                KeyCode::Down => {
+                                    let selected = ui.table_state.selected().unwrap_or(0);
+                                    ui.table_state.select(Some(selected + 1));
+                                    ui.skip_group_header(&ui_state_clone.lock().unwrap(), true);
+                                }
+                                KeyCode::PageUp => {
+                                    let selected = ui.table_state.selected().unwrap_or(0);
+                                    ui.table_state.select(Some(selected.saturating_sub(ui.page_height)));
+                                    ui.skip_group_header(&ui_state_clone.lock().unwrap(), false);
+                                }
+                                // Past the end is clamped to the last row on the next draw
+                                KeyCode::PageDown => {
+                                    let selected = ui.table_state.selected().unwrap_or(0);
+                                    ui.table_state.select(Some(selected + ui.page_height));
+                                    ui.skip_group_header(&ui_state_clone.lock().unwrap(), true);
+                                }
+                                KeyCode::Home => {
+                                    ui.table_state.select(Some(0));
+                                    ui.skip_group_header(&ui_state_clone.lock().unwrap(), true);
+                                }
+                                KeyCode::End => {
+                                    ui.table_state.select(Some(usize::MAX));
+                                }
+                                KeyCode::Char('g') => {
+                                    let mut state = ui_state_clone.lock().unwrap();
+                                    let selected_name = ui.table_state.selected()
+                                        .and_then(|selected| state.operation_at(selected).map(|(name, _)| name.clone()));
+                                    state.group_by_status = !state.group_by_status;
+                                    // Keep the same operation selected under its new row
+                                    if let Some(row) = selected_name.and_then(|name| state.row_of(&name)) {
+                                        ui.table_state.select(Some(row));
+                                    }
+                                }
+                                KeyCode::Enter => {
+                                    let state = ui_state_clone.lock().unwrap();
+                                    if let Some((name, OperationStatus::Completed)) = ui.table_state.selected().and_then(|selected| state.operation_at(selected)) {
+                                        if let Some(url) = state.completed_urls.get(name) {
+                                            let _ = webbrowser::open(url);
                                         }
                                     }
                                 }
+                                KeyCode::Char('r') => {
+                                    let mut state = ui_state_clone.lock().unwrap();
+                                    let selected_name = ui.table_state.selected()
+                                        .and_then(|selected| state.operation_at(selected).map(|(name, _)| name.clone()));
+                                    if let Some(name) = selected_name {
+                                        state.request_retry(&name);
+                                    }
+                                }
+                                KeyCode::Char('a') => {
+                                    let mut state = ui_state_clone.lock().unwrap();
+                                    let selected_name = ui.table_state.selected()
+                                        .and_then(|selected| state.operation_at(selected).map(|(name, _)| name.clone()));
+                                    if let Some(name) = selected_name {
+                                        let message = match state.cycle_upload_age(&name) {
+                                            Some(Some(age)) => format!("Upload age: {}", age),
+                                            Some(None) => "Upload age: default".to_string(),
+                                            None if state.retention_periods.is_empty() => "No retention periods to choose from".to_string(),
+                                            None => "Upload age can only be changed before the upload starts".to_string(),
+                                        };
+                                        state.set_status_message(message);
+                                    }
+                                }
+                                KeyCode::Char('R') => {
+                                    ui_state_clone.lock().unwrap().retry_all_failed();
+                                }
+                                KeyCode::Char('y') => {
+                                    let mut state = ui_state_clone.lock().unwrap();
+                                    let selected_url = ui.table_state.selected()
+                                        .and_then(|selected| state.operation_at(selected).map(|(name, _)| name.clone()))
+                                        .and_then(|name| state.completed_urls.get(&name).cloned());
+                                    let message = match selected_url {
+                                        Some(url) => match ui.copy_to_clipboard(url) {
+                                            Ok(()) => "Copied URL".to_string(),
+                                            Err(e) => format!("Copy failed: {}", e),
+                                        },
+                                        None => "No URL for selected row".to_string(),
+                                    };
+                                    state.set_status_message(message);
+                                }
+                                KeyCode::Char('Y') => {
+                                    let mut state = ui_state_clone.lock().unwrap();
+                                    let urls: Vec<String> = state.completed_urls_sorted().into_iter().cloned().collect();
+                                    let message = if urls.is_empty() {
+                                        "No completed URLs to copy".to_string()
+                                    } else {
+                                        let count = urls.len();
+                                        match ui.copy_to_clipboard(urls.join("\n")) {
+                                            Ok(()) => format!("Copied {} URL{}", count, if count == 1 { "" } else { "s" }),
+                                            Err(e) => format!("Copy failed: {}", e),
+                                        }
+                                    };
+                                    state.set_status_message(message);
+                                }
+                                _ => {}
                             }
-                            _ => {}
                         }
                     }
                 }
             }
-            {
-                let state = ui_state_clone.lock().unwrap();
+            let state = ui_state_clone.lock().unwrap();
+            if key_pressed || drawn_revision != Some(state.revision) || last_draw.elapsed() >= IDLE_REDRAW_INTERVAL {
                 ui.draw(&state).unwrap();
+                drawn_revision = Some(state.revision);
+                last_draw = Instant::now();
             }
-            std::thread::sleep(std::time::Duration::from_millis(16));
         }
         ui.restore().unwrap();
     });
@@ -294,4 +857,36 @@ pub fn stop_ui(handle: std::thread::JoinHandle<()>, running: Arc<AtomicBool>) {
     let mut stdout = io::stdout();
     stdout.execute(terminal::Clear(terminal::ClearType::All)).unwrap();
     stdout.execute(cursor::MoveTo(0, 0)).unwrap();
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restarted_file_is_not_counted_twice() {
+        let state = Mutex::new(UIState::new(1, None, 100));
+        state.on_start("video.mp4", 100);
+        state.on_bytes("video.mp4", 60);
+        // The transfer broke off and starts over from the beginning
+        state.on_start("video.mp4", 100);
+        state.on_bytes("video.mp4", 100);
+        state.on_complete("video.mp4", None);
+
+        let state = state.lock().unwrap();
+        assert_eq!(state.processed_bytes, 100);
+        assert_eq!(state.file_transferred("video.mp4"), 100);
+    }
+
+    #[test]
+    fn other_files_keep_their_progress_when_one_restarts() {
+        let state = Mutex::new(UIState::new(2, None, 300));
+        state.on_start("a.mp4", 100);
+        state.on_start("b.mp4", 200);
+        state.on_bytes("a.mp4", 50);
+        state.on_bytes("b.mp4", 120);
+        state.on_start("a.mp4", 100);
+
+        assert_eq!(state.lock().unwrap().processed_bytes, 120);
+    }
+}
@@ -0,0 +1,123 @@
+mod common;
+
+use bunkr_client::BunkrUploader;
+use common::{config, mount_api, server_config, TOKEN};
+use serde_json::json;
+use wiremock::matchers::{method, path, path_regex};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn albums_are_fetched_from_every_page() {
+    let server = MockServer::start().await;
+    mount_api(&server, server_config()).await;
+    Mock::given(method("GET")).and(path("/api/albums/0"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "success": true,
+            "albums": [{ "id": 1, "name": "Holiday" }, { "id": 2, "name": "Work" }],
+            "count": 3,
+        })))
+        .expect(1)
+        .mount(&server).await;
+    Mock::given(method("GET")).and(path("/api/albums/1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "success": true,
+            "albums": [{ "id": 3, "name": "Pets" }],
+            "count": 3,
+        })))
+        .expect(1)
+        .mount(&server).await;
+
+    let uploader = BunkrUploader::new(TOKEN.to_string(), &config(&server)).await.unwrap();
+    let albums = uploader.refresh_albums().await.unwrap();
+    assert_eq!(albums.iter().map(|a| a.id).collect::<Vec<_>>(), vec![1, 2, 3]);
+    // Found on the second page of the cached list
+    assert_eq!(uploader.get_album_by_name("pets").await.unwrap(), Some(3));
+}
+
+#[tokio::test]
+async fn albums_without_a_count_stop_at_an_empty_page() {
+    let server = MockServer::start().await;
+    mount_api(&server, server_config()).await;
+    Mock::given(method("GET")).and(path("/api/albums/0"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "success": true, "albums": [{ "id": 1, "name": "Holiday" }] })))
+        .mount(&server).await;
+    Mock::given(method("GET")).and(path("/api/albums/1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "success": true, "albums": [] })))
+        .expect(1)
+        .mount(&server).await;
+
+    let uploader = BunkrUploader::new(TOKEN.to_string(), &config(&server)).await.unwrap();
+    assert_eq!(uploader.get_albums().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn album_files_stop_when_the_server_ignores_the_page() {
+    let server = MockServer::start().await;
+    mount_api(&server, server_config()).await;
+    // Every page is the same and there's no count to stop at
+    Mock::given(method("GET")).and(path_regex(r"^/api/album/5/\d+$"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "success": true,
+            "files": [{ "id": 1, "name": "a.jpg", "original": "a.jpg" }, { "id": 2, "name": "b.jpg", "original": "b.jpg" }],
+        })))
+        .mount(&server).await;
+
+    let uploader = BunkrUploader::new(TOKEN.to_string(), &config(&server)).await.unwrap();
+    let files = uploader.get_album_files(5).await.unwrap();
+
+    assert_eq!(files.iter().map(|f| f.id).collect::<Vec<_>>(), vec![1, 2]);
+    let requests = server.received_requests().await.unwrap();
+    assert_eq!(requests.iter().filter(|r| r.url.path().starts_with("/api/album/5/")).count(), 2);
+}
+
+#[tokio::test]
+async fn album_files_are_fetched_page_by_page() {
+    let server = MockServer::start().await;
+    mount_api(&server, server_config()).await;
+    Mock::given(method("GET")).and(path("/api/album/5/0"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "success": true,
+            "files": [{ "id": 1, "original": "a.jpg", "size": 10, "timestamp": 1700000000 }],
+            "count": 2,
+        })))
+        .mount(&server).await;
+    Mock::given(method("GET")).and(path("/api/album/5/1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "success": true,
+            "files": [{ "id": 2, "original": "b.jpg", "size": 20, "timestamp": "1700000001" }],
+            "count": 2,
+        })))
+        .mount(&server).await;
+
+    let uploader = BunkrUploader::new(TOKEN.to_string(), &config(&server)).await.unwrap();
+    let files = uploader.get_album_files(5).await.unwrap();
+
+    assert_eq!(files.len(), 2);
+    assert_eq!((files[1].original.as_str(), files[1].size), ("b.jpg", 20));
+    assert_eq!(files[0].timestamp, "1700000000");
+}
+
+#[tokio::test]
+async fn create_album_without_an_id_is_an_error() {
+    let server = MockServer::start().await;
+    mount_api(&server, server_config()).await;
+    Mock::given(method("POST")).and(path("/api/albums"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "success": true })))
+        .mount(&server).await;
+
+    let uploader = BunkrUploader::new(TOKEN.to_string(), &config(&server)).await.unwrap();
+    let error = uploader.create_album("Trip".to_string(), None, true, true).await.unwrap_err();
+    assert!(error.to_string().contains("did not include an album id"), "{}", error);
+}
+
+#[tokio::test]
+async fn create_album_with_a_malformed_response_is_an_error() {
+    let server = MockServer::start().await;
+    mount_api(&server, server_config()).await;
+    Mock::given(method("POST")).and(path("/api/albums"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("<html>oops</html>"))
+        .mount(&server).await;
+
+    let uploader = BunkrUploader::new(TOKEN.to_string(), &config(&server)).await.unwrap();
+    assert!(uploader.create_album("Trip".to_string(), None, true, true).await.is_err());
+}
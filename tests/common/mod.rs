@@ -0,0 +1,87 @@
+#![allow(dead_code)]
+
+use bunkr_client::core::progress::ProgressSink;
+use bunkr_client::{Config, FailedOperationInfo};
+use serde_json::{json, Value};
+use std::sync::Mutex;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+pub const TOKEN: &str = "test-token";
+
+/// `/api/check` response with a 100MB limit and 25MB chunks.
+pub fn server_config() -> Value {
+    json!({
+        "maintenance": false,
+        "private": false,
+        "enableUserAccounts": true,
+        "maxSize": "100MB",
+        "chunkSize": { "max": "95MB", "default": "25MB", "timeout": 30000 },
+        "fileIdentifierLength": { "min": 4, "max": 32, "default": 8, "force": false },
+        "stripTags": { "default": false, "video": false, "force": false, "blacklistExtensions": [] },
+        "temporaryUploadAges": [],
+        "defaultTemporaryUploadAge": 0,
+    })
+}
+
+/// Mounts token verify, check and node, with uploads going to `/upload` on the same server.
+pub async fn mount_api(server: &MockServer, check: Value) {
+    mount_account(server, check).await;
+    Mock::given(method("GET")).and(path("/api/node"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "success": true, "url": format!("{}/upload", server.uri()) })))
+        .mount(server).await;
+}
+
+/// Mounts token verify and check, leaving the node to the test.
+pub async fn mount_account(server: &MockServer, check: Value) {
+    Mock::given(method("POST")).and(path("/api/tokens/verify"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "success": true, "username": "test" })))
+        .mount(server).await;
+    Mock::given(method("GET")).and(path("/api/check"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(check))
+        .mount(server).await;
+}
+
+/// A config pointing at `server`, without jitter so retries take a predictable time.
+pub fn config(server: &MockServer) -> Config {
+    Config {
+        base_url: Some(server.uri()),
+        download_api_base: Some(server.uri()),
+        retry_jitter: Some(false),
+        ..Config::default()
+    }
+}
+
+/// Writes `content` to `name` in a new temporary directory.
+pub fn temp_file(name: &str, content: &[u8]) -> (tempfile::TempDir, String) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(name);
+    std::fs::write(&path, content).unwrap();
+    (dir, path.to_string_lossy().to_string())
+}
+
+/// Keeps the files it was told completed or failed, and the bytes reported.
+#[derive(Default)]
+pub struct Recorder {
+    pub failed: Mutex<Vec<FailedOperationInfo>>,
+    pub completed: Mutex<Vec<String>>,
+    /// Sum of the bytes reported through `on_bytes`.
+    pub bytes: Mutex<u64>,
+}
+
+impl ProgressSink for Recorder {
+    fn on_start(&self, _path: &str, _size: u64) {}
+    fn on_progress(&self, _path: &str, _progress: f64) {}
+
+    fn on_complete(&self, path: &str, _url: Option<&str>) {
+        self.completed.lock().unwrap().push(path.to_string());
+    }
+
+    fn on_fail(&self, _path: &str, info: &FailedOperationInfo) {
+        self.failed.lock().unwrap().push(info.clone());
+    }
+
+    fn on_bytes(&self, _path: &str, bytes: u64) {
+        *self.bytes.lock().unwrap() += bytes;
+    }
+}
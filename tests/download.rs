@@ -0,0 +1,125 @@
+#![cfg(feature = "download")]
+
+mod common;
+
+use bunkr_client::{AlbumFile, BunkrDownloader};
+use common::{config, Recorder};
+use std::sync::Arc;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Album page listing one file, the way the album page embeds it.
+const ALBUM_PAGE: &str = r#"<html><head><meta property="og:title" content="Holiday &amp; more"></head><body>
+<script>
+window.albumFiles = [
+  {
+    id: 7,
+    name: "beach.jpg",
+    original: "beach.jpg",
+    slug: "beach-slug",
+    type: "image/jpeg",
+    size: 1234,
+    timestamp: "2024-01-01",
+  },
+];
+</script></body></html>"#;
+
+#[tokio::test]
+async fn album_url_with_a_fragment_still_asks_for_the_advanced_listing() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET")).and(path("/a/holiday")).and(query_param("advanced", "1"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(ALBUM_PAGE))
+        .expect(1)
+        .mount(&server).await;
+
+    let downloader = BunkrDownloader::new(&config(&server)).await.unwrap();
+    let files = downloader.get_files(&format!("{}/a/holiday?lang=en#files", server.uri())).await.unwrap();
+
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].original, "beach.jpg");
+    assert_eq!(files[0].size, 1234);
+    assert_eq!(files[0].album, "Holiday & more");
+}
+
+#[tokio::test]
+async fn single_file_page_gives_size_and_type() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET")).and(path("/f/sunset-Ab12Cd34"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(include_str!("fixtures/file_page.html")))
+        .mount(&server).await;
+
+    let downloader = BunkrDownloader::new(&config(&server)).await.unwrap();
+    let files = downloader.get_files(&format!("{}/f/sunset-Ab12Cd34?ref=share", server.uri())).await.unwrap();
+
+    assert_eq!(files.len(), 1);
+    let file = &files[0];
+    assert_eq!(file.id, 48213377);
+    assert_eq!(file.original, "sunset.mp4");
+    assert_eq!(file.slug, "sunset-Ab12Cd34");
+    assert_eq!(file.size, 12_500_000);
+    assert_eq!(file.extension, ".mp4");
+    assert_eq!(file.file_type, "video/mp4");
+    assert_eq!(file.thumbnail, "https://i-burger.bunkr.ru/thumbs/sunset-Ab12Cd34.png");
+}
+
+#[tokio::test]
+async fn failed_download_keeps_its_status_code() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST")).and(path("/api/_001_v2"))
+        .respond_with(ResponseTemplate::new(404).set_body_string("no such file"))
+        // Not found won't change by trying again
+        .expect(1)
+        .mount(&server).await;
+    let output = tempfile::tempdir().unwrap();
+    let file = AlbumFile { id: 7, original: "gone.jpg".to_string(), size: 10, ..Default::default() };
+
+    let downloader = BunkrDownloader::new(&config(&server)).await.unwrap();
+    let recorder = Arc::new(Recorder::default());
+    downloader.download_files(vec![file], &output.path().to_string_lossy(), 1, Some(recorder.clone())).await.unwrap();
+
+    let failed = recorder.failed.lock().unwrap();
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].path, "gone.jpg");
+    assert_eq!(failed[0].status_code, Some(404));
+    assert!(!output.path().join("gone.jpg").exists());
+}
+
+#[tokio::test]
+async fn flaky_album_page_is_retried() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET")).and(path("/a/holiday"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .expect(1)
+        .mount(&server).await;
+    Mock::given(method("GET")).and(path("/a/holiday"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(ALBUM_PAGE))
+        .expect(1)
+        .mount(&server).await;
+
+    let downloader = BunkrDownloader::new(&config(&server)).await.unwrap();
+    let files = downloader.get_files(&format!("{}/a/holiday", server.uri())).await.unwrap();
+
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].original, "beach.jpg");
+}
+
+#[tokio::test]
+async fn flaky_file_page_is_retried() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET")).and(path("/f/sunset-Ab12Cd34"))
+        .respond_with(ResponseTemplate::new(502))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&server).await;
+    Mock::given(method("GET")).and(path("/f/sunset-Ab12Cd34"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(include_str!("fixtures/file_page.html")))
+        .mount(&server).await;
+
+    let downloader = BunkrDownloader::new(&config(&server)).await.unwrap();
+    let files = downloader.get_files(&format!("{}/f/sunset-Ab12Cd34", server.uri())).await.unwrap();
+
+    assert_eq!(files[0].id, 48213377);
+    assert_eq!(server.received_requests().await.unwrap().len(), 2);
+}
@@ -0,0 +1,346 @@
+mod common;
+
+use bunkr_client::{BunkrUploader, Config};
+use common::{config, mount_account, mount_api, server_config, temp_file, Recorder, TOKEN};
+use std::sync::Arc;
+use serde_json::json;
+use wiremock::matchers::{body_string_contains, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn uploads_a_file_through_the_node() {
+    let server = MockServer::start().await;
+    mount_api(&server, server_config()).await;
+    Mock::given(method("POST")).and(path("/upload"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "success": true, "files": [{ "url": "https://cdn.example/hello.txt" }] })))
+        .expect(1)
+        .mount(&server).await;
+    let (_dir, file) = temp_file("hello.txt", b"hello world");
+
+    let config = config(&server);
+    let uploader = BunkrUploader::new(TOKEN.to_string(), &config).await.unwrap();
+    assert_eq!(uploader.upload_url(), format!("{}/upload", server.uri()));
+    let (uploaded, failures) = uploader.upload_files(vec![file.clone()], None, 1, None, Some(&config), None).await.unwrap();
+
+    assert!(failures.is_empty(), "{:?}", failures);
+    assert_eq!(uploaded.len(), 1);
+    assert_eq!(uploaded[0].path, file);
+    assert_eq!(uploaded[0].url, "https://cdn.example/hello.txt");
+    assert_eq!(uploaded[0].file_size, 11);
+
+    let requests = server.received_requests().await.unwrap();
+    let upload = requests.iter().find(|r| r.url.path() == "/upload").unwrap();
+    assert_eq!(upload.headers.get("token").unwrap(), TOKEN);
+    let body = String::from_utf8_lossy(&upload.body);
+    assert!(body.contains(r#"name="files[]"; filename="hello.txt""#), "{}", body);
+    assert!(body.contains("hello world"));
+}
+
+#[tokio::test]
+async fn file_hash_is_sent_with_every_attempt() {
+    const HELLO_SHA256: &str = "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+    let server = MockServer::start().await;
+    mount_api(&server, server_config()).await;
+    Mock::given(method("POST")).and(path("/upload"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&server).await;
+    Mock::given(method("POST")).and(path("/upload")).and(body_string_contains(HELLO_SHA256))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "success": true, "files": [{ "url": "https://cdn.example/hello.txt" }] })))
+        .expect(1)
+        .mount(&server).await;
+    let (_dir, file) = temp_file("hello.txt", b"hello world");
+
+    let config = Config { send_file_hash: Some(true), ..config(&server) };
+    let uploader = BunkrUploader::new(TOKEN.to_string(), &config).await.unwrap();
+    let (uploaded, failures) = uploader.upload_files(vec![file], None, 1, None, Some(&config), None).await.unwrap();
+
+    assert!(failures.is_empty(), "{:?}", failures);
+    assert_eq!(uploaded[0].sha256.as_deref(), Some(HELLO_SHA256));
+    let requests = server.received_requests().await.unwrap();
+    let uploads: Vec<_> = requests.iter().filter(|r| r.url.path() == "/upload").collect();
+    assert_eq!(uploads.len(), 2);
+    for upload in uploads {
+        let body = String::from_utf8_lossy(&upload.body);
+        assert!(body.contains("hello world"), "{}", body);
+        assert!(body.contains(HELLO_SHA256), "{}", body);
+    }
+}
+
+#[tokio::test]
+async fn failed_upload_keeps_its_status_code() {
+    let server = MockServer::start().await;
+    mount_api(&server, server_config()).await;
+    Mock::given(method("POST")).and(path("/upload"))
+        .respond_with(ResponseTemplate::new(413).set_body_string("too large"))
+        // Client errors are neither retried nor sent to another node
+        .expect(1)
+        .mount(&server).await;
+    let (_dir, file) = temp_file("big.bin", b"not really big");
+
+    let config = config(&server);
+    let uploader = BunkrUploader::new(TOKEN.to_string(), &config).await.unwrap();
+    let (uploaded, failures) = uploader.upload_files(vec![file.clone()], None, 1, None, Some(&config), None).await.unwrap();
+
+    assert!(uploaded.is_empty());
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].path, file);
+    assert_eq!(failures[0].status_code, Some(413));
+    assert_eq!(failures[0].file_size, 14);
+}
+
+#[tokio::test]
+async fn transient_server_errors_are_retried() {
+    let server = MockServer::start().await;
+    mount_api(&server, server_config()).await;
+    Mock::given(method("POST")).and(path("/upload"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(2)
+        .with_priority(1)
+        .mount(&server).await;
+    Mock::given(method("POST")).and(path("/upload"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "success": true, "files": [{ "url": "https://cdn.example/flaky.txt" }] })))
+        .mount(&server).await;
+    let (_dir, file) = temp_file("flaky.txt", b"third time lucky");
+
+    let config = config(&server);
+    let uploader = BunkrUploader::new(TOKEN.to_string(), &config).await.unwrap();
+    let (uploaded, failures) = uploader.upload_files(vec![file], None, 1, None, Some(&config), None).await.unwrap();
+
+    assert!(failures.is_empty(), "{:?}", failures);
+    assert_eq!(uploaded[0].url, "https://cdn.example/flaky.txt");
+    assert_eq!(uploaded[0].retries, 2);
+    let requests = server.received_requests().await.unwrap();
+    assert_eq!(requests.iter().filter(|r| r.url.path() == "/upload").count(), 3);
+}
+
+#[tokio::test]
+async fn retry_after_is_honored() {
+    let server = MockServer::start().await;
+    mount_api(&server, server_config()).await;
+    Mock::given(method("POST")).and(path("/upload"))
+        .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "0"))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&server).await;
+    Mock::given(method("POST")).and(path("/upload"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "success": true, "files": [{ "url": "https://cdn.example/busy.txt" }] })))
+        .mount(&server).await;
+    let (_dir, file) = temp_file("busy.txt", b"busy");
+
+    let config = config(&server);
+    let uploader = BunkrUploader::new(TOKEN.to_string(), &config).await.unwrap();
+    let started = std::time::Instant::now();
+    let (uploaded, _) = uploader.upload_files(vec![file], None, 1, None, Some(&config), None).await.unwrap();
+
+    assert_eq!(uploaded[0].retries, 1);
+    // Without the header the first retry waits a full second
+    assert!(started.elapsed() < std::time::Duration::from_millis(900));
+}
+
+#[tokio::test]
+async fn invalid_album_id_fails_the_file_instead_of_panicking() {
+    let server = MockServer::start().await;
+    mount_api(&server, server_config()).await;
+    let (_dir, file) = temp_file("photo.jpg", b"jpeg");
+
+    let config = config(&server);
+    let uploader = BunkrUploader::new(TOKEN.to_string(), &config).await.unwrap();
+    // Can't be sent as the albumid header
+    let (uploaded, failures) = uploader.upload_files(vec![file.clone()], Some("12\n34"), 1, None, Some(&config), None).await.unwrap();
+
+    assert!(uploaded.is_empty());
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].path, file);
+}
+
+#[tokio::test]
+async fn file_whose_task_errors_is_still_reported() {
+    let server = MockServer::start().await;
+    mount_account(&server, server_config()).await;
+    // The node can be fetched once, so switching nodes after the failed upload errors out
+    Mock::given(method("GET")).and(path("/api/node"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "success": true, "url": format!("{}/upload", server.uri()) })))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&server).await;
+    Mock::given(method("GET")).and(path("/api/node"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&server).await;
+    Mock::given(method("POST")).and(path("/upload")).and(body_string_contains(r#"filename="broken.bin""#))
+        .respond_with(ResponseTemplate::new(501))
+        .with_priority(1)
+        .mount(&server).await;
+    Mock::given(method("POST")).and(path("/upload"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "success": true, "files": [{ "url": "https://cdn.example/ok.txt" }] })))
+        .mount(&server).await;
+    let dir = tempfile::tempdir().unwrap();
+    let files: Vec<String> = ["ok.txt", "broken.bin"].iter().map(|name| {
+        let path = dir.path().join(name);
+        std::fs::write(&path, name.as_bytes()).unwrap();
+        path.to_string_lossy().to_string()
+    }).collect();
+
+    let config = config(&server);
+    let uploader = BunkrUploader::new(TOKEN.to_string(), &config).await.unwrap();
+    let (uploaded, failures) = uploader.upload_files(files.clone(), None, 1, None, Some(&config), None).await.unwrap();
+
+    // Every file ends up in exactly one of the lists
+    assert_eq!(uploaded.len() + failures.len(), files.len());
+    assert_eq!(uploaded[0].path, files[0]);
+    assert_eq!(failures[0].path, files[1]);
+    assert!(failures[0].error.contains("Node fetch failed"), "{}", failures[0].error);
+}
+
+#[tokio::test]
+async fn success_without_a_file_url_is_a_failure() {
+    let server = MockServer::start().await;
+    mount_api(&server, server_config()).await;
+    Mock::given(method("POST")).and(path("/upload"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "success": true, "files": [] })))
+        .mount(&server).await;
+    let (_dir, file) = temp_file("ghost.txt", b"boo");
+
+    let config = config(&server);
+    let uploader = BunkrUploader::new(TOKEN.to_string(), &config).await.unwrap();
+    let (uploaded, failures) = uploader.upload_files(vec![file.clone()], None, 1, None, Some(&config), None).await.unwrap();
+
+    assert!(uploaded.is_empty());
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].path, file);
+    assert!(failures[0].error.contains("no file URL"), "{}", failures[0].error);
+}
+
+#[tokio::test]
+async fn finished_chunks_without_a_file_url_are_a_failure() {
+    let server = MockServer::start().await;
+    mount_api(&server, server_config()).await;
+    Mock::given(method("POST")).and(path("/upload"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "success": true })))
+        .mount(&server).await;
+    Mock::given(method("POST")).and(path("/upload/finishchunks"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "success": true })))
+        .mount(&server).await;
+    let (_dir, file) = temp_file("ghost.bin", &[1u8; 15]);
+
+    let config = chunked_config(&server);
+    let uploader = BunkrUploader::new(TOKEN.to_string(), &config).await.unwrap();
+    let (uploaded, failures) = uploader.upload_files(vec![file], None, 1, None, Some(&config), None).await.unwrap();
+
+    assert!(uploaded.is_empty());
+    assert_eq!(failures.len(), 1);
+    assert!(failures[0].error.contains("no file URL"), "{}", failures[0].error);
+}
+
+#[tokio::test]
+async fn maintenance_mode_stops_the_uploader() {
+    let server = MockServer::start().await;
+    let mut check = server_config();
+    check["maintenance"] = json!(true);
+    mount_api(&server, check).await;
+
+    let error = BunkrUploader::new(TOKEN.to_string(), &config(&server)).await.err().unwrap();
+    assert!(error.to_string().contains("maintenance mode"), "{}", error);
+
+    let forced = Config { ignore_maintenance: Some(true), ..config(&server) };
+    let uploader = BunkrUploader::new(TOKEN.to_string(), &forced).await.unwrap();
+    assert!(uploader.is_maintenance());
+    assert!(!uploader.is_private());
+}
+
+#[tokio::test]
+async fn rejects_an_invalid_token() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST")).and(path("/api/tokens/verify"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "success": false })))
+        .mount(&server).await;
+
+    let error = BunkrUploader::new(TOKEN.to_string(), &config(&server)).await.err().unwrap();
+    assert_eq!(error.to_string(), "Invalid API token");
+}
+
+/// Config for `server` that splits uploads into 10 byte chunks.
+fn chunked_config(server: &MockServer) -> Config {
+    Config {
+        chunk_size: Some("10B".to_string()),
+        ..config(server)
+    }
+}
+
+#[tokio::test]
+async fn chunked_upload_finishes_once_every_chunk_is_stored() {
+    let server = MockServer::start().await;
+    mount_api(&server, server_config()).await;
+    Mock::given(method("POST")).and(path("/upload"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "success": true })))
+        .expect(3)
+        .mount(&server).await;
+    Mock::given(method("POST")).and(path("/upload/finishchunks"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "success": true, "files": [{ "url": "https://cdn.example/chunked.bin" }] })))
+        .expect(1)
+        .mount(&server).await;
+    let (_dir, file) = temp_file("chunked.bin", &[7u8; 25]);
+
+    let config = chunked_config(&server);
+    let uploader = BunkrUploader::new(TOKEN.to_string(), &config).await.unwrap();
+    let (uploaded, failures) = uploader.upload_files(vec![file], None, 1, None, Some(&config), None).await.unwrap();
+
+    assert!(failures.is_empty(), "{:?}", failures);
+    assert_eq!(uploaded[0].url, "https://cdn.example/chunked.bin");
+}
+
+#[tokio::test]
+async fn retried_chunk_is_counted_once() {
+    let server = MockServer::start().await;
+    mount_api(&server, server_config()).await;
+    Mock::given(method("POST")).and(path("/upload"))
+        .and(body_string_contains("name=\"dzchunkindex\"\r\n\r\n1\r\n"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&server).await;
+    Mock::given(method("POST")).and(path("/upload"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "success": true })))
+        .mount(&server).await;
+    Mock::given(method("POST")).and(path("/upload/finishchunks"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "success": true, "files": [{ "url": "https://cdn.example/chunked.bin" }] })))
+        .mount(&server).await;
+    let (_dir, file) = temp_file("chunked.bin", &[7u8; 25]);
+
+    let config = chunked_config(&server);
+    let uploader = BunkrUploader::new(TOKEN.to_string(), &config).await.unwrap();
+    let recorder = Arc::new(Recorder::default());
+    let (uploaded, _) = uploader.upload_files(vec![file], None, 1, Some(recorder.clone()), Some(&config), None).await.unwrap();
+
+    assert_eq!(uploaded[0].retries, 1);
+    assert_eq!(*recorder.bytes.lock().unwrap(), 25);
+}
+
+#[tokio::test]
+async fn lost_chunk_stops_the_upload_before_finishing() {
+    let server = MockServer::start().await;
+    mount_api(&server, server_config()).await;
+    // The second chunk gets a response that doesn't confirm it was stored
+    Mock::given(method("POST")).and(path("/upload"))
+        .and(body_string_contains("name=\"dzchunkindex\"\r\n\r\n1\r\n"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(""))
+        .with_priority(1)
+        .mount(&server).await;
+    Mock::given(method("POST")).and(path("/upload"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "success": true })))
+        .mount(&server).await;
+    Mock::given(method("POST")).and(path("/upload/finishchunks"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "success": true, "files": [{ "url": "https://cdn.example/chunked.bin" }] })))
+        .expect(0)
+        .mount(&server).await;
+    let (_dir, file) = temp_file("chunked.bin", &[7u8; 25]);
+
+    let config = chunked_config(&server);
+    let uploader = BunkrUploader::new(TOKEN.to_string(), &config).await.unwrap();
+    let (uploaded, failures) = uploader.upload_files(vec![file], None, 1, None, Some(&config), None).await.unwrap();
+
+    assert!(uploaded.is_empty());
+    assert_eq!(failures.len(), 1);
+    assert!(failures[0].error.contains("Only 2 of 3 chunks"), "{}", failures[0].error);
+}